@@ -0,0 +1,72 @@
+//! Drawing of maps as pictures, on top of the screen coordinates of `layout`.
+
+use pos::Position;
+use layout::{Layout,Point};
+
+pub use self::svg::Svg;
+#[cfg(feature="raster")]
+pub use self::raster::{Raster,Image,Marker};
+
+mod svg;
+#[cfg(feature="raster")]
+mod raster;
+
+/// A color with an alpha channel, `a` being `0` for transparent and `255` for opaque
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+pub struct Color {
+    pub r : u8,
+    pub g : u8,
+    pub b : u8,
+    pub a : u8
+}
+
+impl Color {
+    pub const BLACK : Color = Color {r:0,g:0,b:0,a:255};
+    pub const WHITE : Color = Color {r:255,g:255,b:255,a:255};
+    pub const TRANSPARENT : Color = Color {r:0,g:0,b:0,a:0};
+
+    pub fn rgb(r:u8,g:u8,b:u8) -> Color {
+        Color {r,g,b,a:255}
+    }
+
+    pub fn rgba(r:u8,g:u8,b:u8,a:u8) -> Color {
+        Color {r,g,b,a}
+    }
+}
+
+/// How to draw a hex: its inside is filled with `fill`, and its edges are `stroke_width`
+/// wide lines of `stroke`.
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub struct Style {
+    pub fill : Color,
+    pub stroke : Color,
+    pub stroke_width : f64
+}
+
+impl Style {
+    /// A style with thin black edges
+    pub fn new(fill:Color) -> Style {
+        Style {fill,stroke:Color::BLACK,stroke_width:1.0}
+    }
+
+    pub fn with_stroke(self,stroke:Color,stroke_width:f64) -> Style {
+        Style {stroke,stroke_width,..self}
+    }
+}
+
+/// Top-left and bottom-right corners of the smallest box holding the hexes of `positions`,
+/// grown by `margin` on every side
+fn bounds<I>(layout:&Layout,positions:I,margin:f64) -> (Point,Point) where I : IntoIterator<Item=Position> {
+    let corners = positions.into_iter().flat_map(|position| layout.hex_corners(position).to_vec());
+    let mut bounds : Option<(Point,Point)> = None;
+    for corner in corners {
+        bounds = Some(match bounds {
+            None => (corner,corner),
+            Some((min,max)) => (Point::new(min.x.min(corner.x),min.y.min(corner.y)),
+                                Point::new(max.x.max(corner.x),max.y.max(corner.y))),
+        });
+    }
+    let (min,max) = bounds.unwrap_or_default();
+    let margin = Point::new(margin,margin);
+    (min - margin,max + margin)
+}
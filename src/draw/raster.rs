@@ -0,0 +1,483 @@
+use std::f64::consts::PI;
+use pos::Position;
+use layout::{Layout,Point};
+use map::HexMap;
+use draw::{Color,Style,bounds};
+
+/// Side of the grid of samples taken in every pixel, which smooths the edges of shapes
+const SAMPLES : usize = 4;
+
+const PNG_SIGNATURE : [u8;8] = [137,80,78,71,13,10,26,10];
+
+/// Largest amount of data in a stored deflate block
+const STORED_BLOCK_LEN : usize = 65535;
+
+/// Picture made of RGBA pixels, not premultiplied, stored row by row from the top-left one.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Image {
+    width:u32,
+    height:u32,
+    pixels:Vec<u8>
+}
+
+impl Image {
+    pub fn new(width:u32,height:u32,background:Color) -> Image {
+        let len = width as usize * height as usize;
+        let mut pixels = Vec::with_capacity(len * 4);
+        for _ in 0 .. len {
+            pixels.extend_from_slice(&[background.r,background.g,background.b,background.a]);
+        }
+        Image {width,height,pixels}
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// 4 bytes per pixel: red, green, blue and alpha
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    fn index(&self,x:u32,y:u32) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some((y as usize * self.width as usize + x as usize) * 4)
+        } else {
+            None
+        }
+    }
+
+    /// `None` if `(x,y)` is outside of the image
+    pub fn get_pixel(&self,x:u32,y:u32) -> Option<Color> {
+        self.index(x,y).map(|i| Color::rgba(self.pixels[i],self.pixels[i + 1],self.pixels[i + 2],self.pixels[i + 3]))
+    }
+
+    /// Does nothing if `(x,y)` is outside of the image
+    pub fn set_pixel(&mut self,x:u32,y:u32,color:Color) {
+        if let Some(i) = self.index(x,y) {
+            self.pixels[i .. i + 4].copy_from_slice(&[color.r,color.g,color.b,color.a]);
+        }
+    }
+
+    /// Paints `color` over the pixel `(x,y)`, `coverage` being the part of the pixel it
+    /// covers, from `0.0` to `1.0`.
+    pub fn blend_pixel(&mut self,x:u32,y:u32,color:Color,coverage:f64) {
+        let i = match self.index(x,y) {
+            Some(i) => i,
+            None => return
+        };
+        let alpha = color.a as f64 / 255.0 * coverage.clamp(0.0,1.0);
+        if alpha <= 0.0 {
+            return;
+        }
+        let dst_alpha = self.pixels[i + 3] as f64 / 255.0;
+        let out_alpha = alpha + dst_alpha * (1.0 - alpha);
+        for (channel,&src) in [color.r,color.g,color.b].iter().enumerate() {
+            let dst = self.pixels[i + channel] as f64;
+            let out = (src as f64 * alpha + dst * dst_alpha * (1.0 - alpha)) / out_alpha;
+            self.pixels[i + channel] = out.round() as u8;
+        }
+        self.pixels[i + 3] = (out_alpha * 255.0).round() as u8;
+    }
+
+    /// Paints the inside of a polygon made of one or more closed `contours`, in pixel
+    /// coordinates. A point is inside when a ray going out of it crosses the contours an odd
+    /// number of times, so that a contour inside another one makes a hole.
+    ///
+    /// Edges are anti-aliased by sampling every pixel several times. Contours with less
+    /// than 3 points, or with a point which is not finite, are left out.
+    pub fn fill_polygon(&mut self,contours:&[Vec<Point>],color:Color) {
+        let contours : Vec<&Vec<Point>> = contours.iter()
+            .filter(|contour| contour.len() >= 3 && contour.iter().all(|p| p.x.is_finite() && p.y.is_finite()))
+            .collect();
+        let points = || contours.iter().flat_map(|contour| contour.iter());
+        let min_y = points().fold(f64::INFINITY,|min,p| min.min(p.y)).floor().max(0.0);
+        let max_y = points().fold(f64::NEG_INFINITY,|max,p| max.max(p.y)).ceil().min(self.height as f64);
+        if min_y >= max_y {
+            return;
+        }
+        let samples_per_row = self.width as usize * SAMPLES;
+        let mut coverage = vec![0usize;self.width as usize];
+        let mut crossings = Vec::new();
+        for y in min_y as u32 .. max_y as u32 {
+            for count in coverage.iter_mut() {
+                *count = 0;
+            }
+            for i in 0 .. SAMPLES {
+                let sample_y = y as f64 + (i as f64 + 0.5) / SAMPLES as f64;
+                crossings.clear();
+                for contour in &contours {
+                    for (k,&a) in contour.iter().enumerate() {
+                        let b = contour[(k + 1) % contour.len()];
+                        if (a.y <= sample_y) != (b.y <= sample_y) {
+                            crossings.push(a.x + (sample_y - a.y) * (b.x - a.x) / (b.y - a.y));
+                        }
+                    }
+                }
+                crossings.sort_by(f64::total_cmp);
+                for span in crossings.chunks(2).filter(|span| span.len() == 2) {
+                    // samples of the row are at `(k + 0.5) / SAMPLES`, keep the ones in the span
+                    let first = (span[0] * SAMPLES as f64 - 0.5).ceil().max(0.0) as usize;
+                    let last = ((span[1] * SAMPLES as f64 - 0.5).ceil().max(0.0) as usize).min(samples_per_row);
+                    for k in first .. last {
+                        coverage[k / SAMPLES] += 1;
+                    }
+                }
+            }
+            for (x,&count) in coverage.iter().enumerate().filter(|&(_,&count)| count > 0) {
+                self.blend_pixel(x as u32,y,color,count as f64 / (SAMPLES * SAMPLES) as f64);
+            }
+        }
+    }
+
+    /// Encodes the image as a PNG file.
+    ///
+    /// The pixels are not compressed: the image data is made of stored deflate blocks, which
+    /// every PNG reader supports and which are quick to write.
+    pub fn to_png(&self) -> Vec<u8> {
+        let row_len = self.width as usize * 4;
+        let mut raw = Vec::with_capacity((row_len + 1) * self.height as usize);
+        for y in 0 .. self.height as usize {
+            // filter type `None`
+            raw.push(0);
+            raw.extend_from_slice(&self.pixels[y * row_len .. (y + 1) * row_len]);
+        }
+
+        // zlib stream: no compression, 32K window
+        let mut zlib = vec![0x78,0x01];
+        let blocks = raw.len().div_ceil(STORED_BLOCK_LEN).max(1);
+        for i in 0 .. blocks {
+            let block = &raw[i * STORED_BLOCK_LEN .. ((i + 1) * STORED_BLOCK_LEN).min(raw.len())];
+            zlib.push((i + 1 == blocks) as u8);
+            zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+            zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlace
+        header.extend_from_slice(&[8,6,0,0,0]);
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png,b"IHDR",&header);
+        write_chunk(&mut png,b"IDAT",&zlib);
+        write_chunk(&mut png,b"IEND",&[]);
+        png
+    }
+}
+
+fn crc32(bytes:&[u8]) -> u32 {
+    let mut table = [0u32;256];
+    for (n,entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0 .. 8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+    !bytes.iter().fold(!0u32,|c,&byte| table[((c ^ byte as u32) & 0xff) as usize] ^ (c >> 8))
+}
+
+fn adler32(bytes:&[u8]) -> u32 {
+    const MOD : u32 = 65521;
+    let (mut a,mut b) = (1u32,0u32);
+    // sums cannot overflow within 5552 bytes
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+fn write_chunk(png:&mut Vec<u8>,kind:&[u8;4],data:&[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start ..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Shape drawn at the center of a hex by `Raster::marker`, about two thirds of its width
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+pub enum Marker {
+    Dot,
+    Square,
+    Diamond,
+    Cross
+}
+
+impl Marker {
+    /// Contour of the marker around `(0,0)`, for hexes of radius `size`
+    fn contour(self,size:f64) -> Vec<Point> {
+        let r = size * 0.35;
+        match self {
+            Marker::Dot => (0 .. 24).map(|i| {
+                let angle = PI * i as f64 / 12.0;
+                Point::new(r * angle.cos(),r * angle.sin())
+            }).collect(),
+            Marker::Square => vec![Point::new(-r,-r),Point::new(r,-r),Point::new(r,r),Point::new(-r,r)],
+            Marker::Diamond => vec![Point::new(0.0,-r),Point::new(r,0.0),Point::new(0.0,r),Point::new(-r,0.0)],
+            Marker::Cross => {
+                let t = r * 0.35;
+                [(-t,-r),(t,-r),(t,-t),(r,-t),(r,t),(t,t),(t,r),(-t,r),(-t,t),(-r,t),(-r,-t),(-t,-t)]
+                    .iter().map(|&(x,y)| Point::new(x,y)).collect()
+            },
+        }
+    }
+}
+
+/// Software renderer of maps into `Image`s, the raster counterpart of `Svg`.
+///
+/// Every position of the map is drawn as a hex styled by a callback, then come the
+/// overlays and finally the markers. Screen coordinates are the ones of the `Layout`, one
+/// screen unit being one pixel, and the image is cropped to the hexes it draws plus the
+/// margin.
+///
+/// ```
+/// use hexgrid::{Map,AllowContent,PositionAccessor};
+/// use hexgrid::pos::Position;
+/// use hexgrid::layout::{Layout,Orientation,Point};
+/// use hexgrid::draw::{Raster,Marker,Style,Color};
+/// # #[derive(Default)]
+/// # struct Ground;
+/// # impl AllowContent for Ground {
+/// #     fn is_content_allowed(&self) -> bool { true }
+/// # }
+/// # struct Unit { position:Position }
+/// # impl PositionAccessor for Unit {
+/// #     fn set_position(&mut self,new_position:Position) { self.position = new_position; }
+/// #     fn get_position(&self) -> Position { self.position }
+/// # }
+/// let map : Map<Unit,Ground> = Map::new((4,4),Position::new(0,0)).unwrap();
+/// let layout = Layout::new(Orientation::Flat,Point::new(12.0,12.0),Point::new(0.0,0.0));
+/// let image = Raster::new(layout)
+///     .marker(vec![Position::new(1,2)],Marker::Dot,Color::rgb(200,0,0))
+///     .render(&map,|_,_,_| Style::new(Color::rgb(40,160,40)));
+/// let png = image.to_png();
+/// assert_eq!(&png[1 .. 4],b"PNG");
+/// ```
+#[derive(Clone,Debug)]
+pub struct Raster {
+    layout:Layout,
+    margin:f64,
+    background:Color,
+    overlays:Vec<(Vec<Position>,Style)>,
+    markers:Vec<(Vec<Position>,Marker,Color)>
+}
+
+impl Raster {
+    pub fn new(layout:Layout) -> Raster {
+        Raster {
+            layout,
+            margin:0.0,
+            background:Color::TRANSPARENT,
+            overlays:Vec::new(),
+            markers:Vec::new()
+        }
+    }
+
+    /// Room left around the map, in pixels
+    pub fn margin(self,margin:f64) -> Raster {
+        Raster {margin,..self}
+    }
+
+    /// Color of the pixels out of every hex, transparent by default
+    pub fn background(self,background:Color) -> Raster {
+        Raster {background,..self}
+    }
+
+    /// Draws `positions` again above the map with `style`, see `Svg::overlay`
+    pub fn overlay(mut self,positions:Vec<Position>,style:Style) -> Raster {
+        self.overlays.push((positions,style));
+        self
+    }
+
+    /// Draws `marker` with `color` at the center of every position of `positions`
+    pub fn marker(mut self,positions:Vec<Position>,marker:Marker,color:Color) -> Raster {
+        self.markers.push((positions,marker,color));
+        self
+    }
+
+    fn hex(&self,image:&mut Image,origin:Point,position:Position,style:Style) {
+        let center = self.layout.hex_to_pixel(position) - origin;
+        let corners : Vec<Point> = self.layout.hex_corners(position).iter().map(|&corner| corner - origin).collect();
+        image.fill_polygon(::std::slice::from_ref(&corners),style.fill);
+        if style.stroke_width > 0.0 && style.stroke.a > 0 {
+            // the edges are moved by half of the width on both sides, an edge being at
+            // `sqrt(3) / 2` of the radius from the center
+            let apothem = self.layout.size.x.min(self.layout.size.y) * 3f64.sqrt() / 2.0;
+            if apothem <= 0.0 {
+                return;
+            }
+            let grow = |scale:f64| -> Vec<Point> {
+                corners.iter().map(|&corner| center + (corner - center) * scale).collect()
+            };
+            let half = style.stroke_width / 2.0 / apothem;
+            image.fill_polygon(&[grow(1.0 + half),grow((1.0 - half).max(0.0))],style.stroke);
+        }
+    }
+
+    /// Draws `map` into a new image, the style of every position being given by `style`
+    /// from its position, content and background.
+    pub fn render<M,F>(&self,map:&M,mut style:F) -> Image
+        where M : HexMap, F : FnMut(Position,Option<&M::Content>,&M::Bg) -> Style {
+        let tiles : Vec<(Position,Style)> = map.iter()
+            .map(|(position,content,bg)| (position,style(position,content,bg)))
+            .collect();
+        let drawn = tiles.iter().map(|&(position,_)| position)
+            .chain(self.overlays.iter().flat_map(|overlay| overlay.0.iter().cloned()))
+            .chain(self.markers.iter().flat_map(|marker| marker.0.iter().cloned()));
+        let (min,max) = bounds(&self.layout,drawn,self.margin);
+        let size = max - min;
+        let mut image = Image::new(size.x.ceil().max(0.0) as u32,size.y.ceil().max(0.0) as u32,self.background);
+
+        for &(position,style) in &tiles {
+            self.hex(&mut image,min,position,style);
+        }
+        for &(ref positions,style) in &self.overlays {
+            for &position in positions {
+                self.hex(&mut image,min,position,style);
+            }
+        }
+        let hex_size = self.layout.size.x.min(self.layout.size.y);
+        for &(ref positions,marker,color) in &self.markers {
+            let contour = marker.contour(hex_size);
+            for &position in positions {
+                let center = self.layout.hex_to_pixel(position) - min;
+                let contour = contour.iter().map(|&point| center + point).collect();
+                image.fill_polygon(&[contour],color);
+            }
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use layout::Orientation;
+    use map::Map;
+    use map::tests::{Dummy,Bg};
+
+    /// Image data of a PNG written by `to_png`, read back from its stored blocks
+    fn inflate_stored(png:&[u8]) -> Vec<u8> {
+        let len = u32::from_be_bytes([png[33],png[34],png[35],png[36]]) as usize;
+        assert_eq!(&png[37 .. 41],b"IDAT");
+        let zlib = &png[41 .. 41 + len];
+        assert_eq!(&zlib[.. 2],&[0x78,0x01]);
+        let mut raw = Vec::new();
+        let mut i = 2;
+        loop {
+            let last = zlib[i] == 1;
+            let block_len = u16::from_le_bytes([zlib[i + 1],zlib[i + 2]]) as usize;
+            assert_eq!(!block_len as u16,u16::from_le_bytes([zlib[i + 3],zlib[i + 4]]));
+            raw.extend_from_slice(&zlib[i + 5 .. i + 5 + block_len]);
+            i += 5 + block_len;
+            if last {
+                break;
+            }
+        }
+        assert_eq!(&zlib[i ..],&adler32(&raw).to_be_bytes());
+        raw
+    }
+
+    #[test]
+    fn checksums(){
+        assert_eq!(crc32(b"123456789"),0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"),0x11e6_0398);
+        assert_eq!(adler32(&[255;100_000]),{
+            let (a,b) = (1 + 255 * 100_000u64,(1..=100_000u64).map(|n| 1 + 255 * n).sum::<u64>());
+            (((b % 65521) << 16) | (a % 65521)) as u32
+        });
+    }
+
+    #[test]
+    fn png(){
+        let mut image = Image::new(200,100,Color::rgba(1,2,3,4));
+        image.set_pixel(199,99,Color::WHITE);
+        let png = image.to_png();
+        assert_eq!(&png[.. 8],&PNG_SIGNATURE);
+        assert_eq!(&png[12 .. 16],b"IHDR");
+        assert_eq!(&png[16 .. 24],&[0,0,0,200,0,0,0,100]);
+        assert_eq!(&png[png.len() - 12 ..],&[0,0,0,0,b'I',b'E',b'N',b'D',0xae,0x42,0x60,0x82]);
+        let raw = inflate_stored(&png);
+        assert_eq!(raw.len(),(200 * 4 + 1) * 100);
+        assert_eq!(&raw[.. 5],&[0,1,2,3,4]);
+        assert_eq!(&raw[raw.len() - 4 ..],&[255,255,255,255]);
+
+        let empty = Image::new(0,0,Color::BLACK).to_png();
+        assert!(inflate_stored(&empty).is_empty());
+    }
+
+    #[test]
+    fn polygon(){
+        let mut image = Image::new(10,10,Color::TRANSPARENT);
+        let square = vec![Point::new(2.0,2.0),Point::new(8.0,2.0),Point::new(8.0,8.0),Point::new(2.0,8.0)];
+        let hole = vec![Point::new(4.0,4.0),Point::new(6.0,4.0),Point::new(6.0,6.0),Point::new(4.0,6.0)];
+        image.fill_polygon(&[square,hole],Color::rgb(255,0,0));
+        assert_eq!(image.get_pixel(2,2),Some(Color::rgb(255,0,0)));
+        assert_eq!(image.get_pixel(5,5),Some(Color::TRANSPARENT));
+        assert_eq!(image.get_pixel(1,5),Some(Color::TRANSPARENT));
+
+        // half of the pixels of the column are covered
+        let mut image = Image::new(4,4,Color::WHITE);
+        image.fill_polygon(&[vec![Point::new(0.0,0.0),Point::new(1.5,0.0),Point::new(1.5,4.0),Point::new(0.0,4.0)]],Color::BLACK);
+        assert_eq!(image.get_pixel(0,0),Some(Color::BLACK));
+        assert_eq!(image.get_pixel(1,3),Some(Color::rgb(128,128,128)));
+        assert_eq!(image.get_pixel(2,0),Some(Color::WHITE));
+    }
+
+    #[test]
+    fn render(){
+        let map : Map<Dummy,Bg> = Map::new((3,3),Position::new(0,0)).unwrap();
+        let layout = Layout::new(Orientation::Pointy,Point::new(10.0,10.0),Point::new(0.0,0.0));
+        let green = Color::rgb(0,200,0);
+        let image = Raster::new(layout).margin(3.0)
+            .overlay(vec![Position::new(2,2)],Style::new(Color::rgba(0,0,255,128)).with_stroke(Color::TRANSPARENT,0.0))
+            .marker(vec![Position::new(0,0)],Marker::Cross,Color::rgb(255,0,0))
+            .render(&map,|_,_,_| Style::new(green).with_stroke(Color::BLACK,2.0));
+        let (min,_) = bounds(&layout,map.iter_bg().map(|(position,_)| position),3.0);
+        let pixel = |point:Point| {
+            let point = point - min;
+            image.get_pixel(point.x as u32,point.y as u32).unwrap()
+        };
+        assert_eq!(image.get_pixel(0,0),Some(Color::TRANSPARENT));
+        assert_eq!(pixel(layout.hex_to_pixel(Position::new(1,1)) + Point::new(3.0,2.0)),green);
+        assert_eq!(pixel(layout.hex_to_pixel(Position::new(0,0))),Color::rgb(255,0,0));
+        let blended = pixel(layout.hex_to_pixel(Position::new(2,2)) + Point::new(3.0,2.0));
+        assert_eq!((blended.r,blended.a),(0,255));
+        assert!(blended.g > 90 && blended.g < 110 && blended.b > 120 && blended.b < 135);
+        // edges are black, with some anti-aliasing around them
+        let edge = pixel(layout.edge_midpoint(Position::new(1,1),::pos::MainDirection::E));
+        assert!(edge.g < 100);
+    }
+
+    #[test]
+    fn degenerate(){
+        let mut image = Image::new(10,10,Color::TRANSPARENT);
+        let square = vec![Point::new(2.0,2.0),Point::new(8.0,2.0),Point::new(8.0,8.0),Point::new(2.0,8.0)];
+        let broken = vec![Point::new(4.0,4.0),Point::new(f64::NAN,4.0),Point::new(6.0,f64::INFINITY)];
+        image.fill_polygon(&[square,broken,vec![Point::new(1.0,1.0),Point::new(9.0,9.0)]],Color::BLACK);
+        assert_eq!(image.get_pixel(5,5),Some(Color::BLACK));
+        assert_eq!(image.get_pixel(1,5),Some(Color::TRANSPARENT));
+
+        // hexes of size 0 draw nothing, whatever their stroke
+        let map : Map<Dummy,Bg> = Map::new((3,3),Position::new(0,0)).unwrap();
+        let layout = Layout::new(Orientation::Flat,Point::new(0.0,0.0),Point::new(0.0,0.0));
+        let image = Raster::new(layout).margin(2.0)
+            .render(&map,|_,_,_| Style::new(Color::WHITE).with_stroke(Color::BLACK,1.0));
+        assert_eq!(image.get_pixel(2,2),Some(Color::TRANSPARENT));
+    }
+}
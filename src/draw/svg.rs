@@ -0,0 +1,220 @@
+use std::fmt::Write;
+use pos::Position;
+use layout::{Layout,Point};
+use map::HexMap;
+use draw::{Color,Style,bounds};
+
+/// Writer of maps as SVG pictures.
+///
+/// Every position of the map is drawn as a polygon styled by a callback. On top of them
+/// come the overlays, which highlight some positions (results of `Position::in_range`,
+/// `Position::in_cone`, `HexMap::field_of_view`...), then the paths, drawn as arrows from
+/// hex to hex, and finally the labels with the coordinates of every position.
+///
+/// ```
+/// use hexgrid::{Map,AllowContent,PositionAccessor};
+/// use hexgrid::pos::Position;
+/// use hexgrid::layout::{Layout,Orientation,Point};
+/// use hexgrid::draw::{Svg,Style,Color};
+/// # #[derive(Default)]
+/// # struct Ground;
+/// # impl AllowContent for Ground {
+/// #     fn is_content_allowed(&self) -> bool { true }
+/// # }
+/// # struct Unit { position:Position }
+/// # impl PositionAccessor for Unit {
+/// #     fn set_position(&mut self,new_position:Position) { self.position = new_position; }
+/// #     fn get_position(&self) -> Position { self.position }
+/// # }
+/// let map : Map<Unit,Ground> = Map::new((4,4),Position::new(0,0)).unwrap();
+/// let layout = Layout::new(Orientation::Pointy,Point::new(20.0,20.0),Point::new(0.0,0.0));
+/// let svg = Svg::new(layout)
+///     .labels(true)
+///     .overlay(Position::new(1,1).in_range(1),Style::new(Color::rgba(255,0,0,96)))
+///     .path(vec![Position::new(0,0),Position::new(1,0),Position::new(1,1)],Color::rgb(0,0,255))
+///     .render(&map,|_,_,_| Style::new(Color::WHITE));
+/// assert!(svg.starts_with("<svg"));
+/// ```
+#[derive(Clone,Debug)]
+pub struct Svg {
+    layout:Layout,
+    labels:bool,
+    margin:f64,
+    overlays:Vec<(Vec<Position>,Style)>,
+    paths:Vec<(Vec<Position>,Color)>
+}
+
+/// Writes `value` with at most 2 decimals
+fn number(value:f64) -> String {
+    let text = format!("{:.2}",value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        String::from("0")
+    } else {
+        String::from(text)
+    }
+}
+
+fn points(points:&[Point]) -> String {
+    let points : Vec<String> = points.iter().map(|p| format!("{},{}",number(p.x),number(p.y))).collect();
+    points.join(" ")
+}
+
+/// Color and opacity attributes of `color` for the `attribute` property (`fill`, `stroke`)
+fn paint(attribute:&str,color:Color) -> String {
+    let mut text = format!(r##"{}="#{:02x}{:02x}{:02x}""##,attribute,color.r,color.g,color.b);
+    if color.a != 255 {
+        text.push_str(&format!(r#" {}-opacity="{}""#,attribute,number(color.a as f64 / 255.0)));
+    }
+    text
+}
+
+impl Svg {
+    pub fn new(layout:Layout) -> Svg {
+        Svg {
+            layout,
+            labels:false,
+            margin:0.0,
+            overlays:Vec::new(),
+            paths:Vec::new()
+        }
+    }
+
+    /// Whether to write the coordinates of every position of the map on its hex
+    pub fn labels(self,labels:bool) -> Svg {
+        Svg {labels,..self}
+    }
+
+    /// Room left around the map, in screen units
+    pub fn margin(self,margin:f64) -> Svg {
+        Svg {margin,..self}
+    }
+
+    /// Draws `positions` again above the map with `style`. Overlays are drawn in the order
+    /// they are added, so a translucent `fill` lets the map be seen through them.
+    pub fn overlay(mut self,positions:Vec<Position>,style:Style) -> Svg {
+        self.overlays.push((positions,style));
+        self
+    }
+
+    /// Draws an arrow from the center of every position of `path` to the next one
+    pub fn path(mut self,path:Vec<Position>,color:Color) -> Svg {
+        self.paths.push((path,color));
+        self
+    }
+
+    fn polygon(&self,text:&mut String,position:Position,style:Style) {
+        let _ = writeln!(text,r#"<polygon points="{}" {} {} stroke-width="{}"/>"#,
+                         points(&self.layout.hex_corners(position)),
+                         paint("fill",style.fill),paint("stroke",style.stroke),number(style.stroke_width));
+    }
+
+    /// Writes the SVG document of `map`, the style of every position being given by
+    /// `style` from its position, content and background.
+    ///
+    /// The picture is cropped to the hexes it draws, plus the margin.
+    pub fn render<M,F>(&self,map:&M,mut style:F) -> String
+        where M : HexMap, F : FnMut(Position,Option<&M::Content>,&M::Bg) -> Style {
+        let tiles : Vec<(Position,Style)> = map.iter()
+            .map(|(position,content,bg)| (position,style(position,content,bg)))
+            .collect();
+        let drawn = tiles.iter().map(|&(position,_)| position)
+            .chain(self.overlays.iter().flat_map(|overlay| overlay.0.iter().cloned()))
+            .chain(self.paths.iter().flat_map(|path| path.0.iter().cloned()));
+        let (min,max) = bounds(&self.layout,drawn,self.margin);
+        let size = max - min;
+        let hex_size = self.layout.size.x.min(self.layout.size.y);
+
+        let mut text = String::new();
+        let _ = writeln!(text,r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+                         number(size.x),number(size.y),number(min.x),number(min.y),number(size.x),number(size.y));
+        if !self.paths.is_empty() {
+            text.push_str("<defs>\n");
+            for (i,&(_,color)) in self.paths.iter().enumerate() {
+                let _ = writeln!(text,r#"<marker id="arrow-{}" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="4" markerHeight="4" orient="auto"><path d="M0,0 L10,5 L0,10 z" {}/></marker>"#,
+                                 i,paint("fill",color));
+            }
+            text.push_str("</defs>\n");
+        }
+        for &(position,style) in &tiles {
+            self.polygon(&mut text,position,style);
+        }
+        for &(ref positions,style) in &self.overlays {
+            for &position in positions {
+                self.polygon(&mut text,position,style);
+            }
+        }
+        for (i,&(ref path,color)) in self.paths.iter().enumerate() {
+            for step in path.windows(2) {
+                let (from,to) = (self.layout.hex_to_pixel(step[0]),self.layout.hex_to_pixel(step[1]));
+                let _ = writeln!(text,r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {} stroke-width="{}" marker-end="url(#arrow-{})"/>"#,
+                                 number(from.x),number(from.y),number(to.x),number(to.y),
+                                 paint("stroke",color),number(hex_size * 0.1),i);
+            }
+        }
+        if self.labels {
+            for &(position,_) in &tiles {
+                let center = self.layout.hex_to_pixel(position);
+                let _ = writeln!(text,r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="central">{},{}</text>"#,
+                                 number(center.x),number(center.y),number(hex_size * 0.4),position.x,position.y);
+            }
+        }
+        text.push_str("</svg>\n");
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use layout::Orientation;
+    use map::Map;
+    use map::tests::{Dummy,Bg};
+
+    fn layout(orientation:Orientation) -> Layout {
+        Layout::new(orientation,Point::new(10.0,10.0),Point::new(0.0,0.0))
+    }
+
+    #[test]
+    fn numbers(){
+        assert_eq!(number(1.0),"1");
+        assert_eq!(number(-0.001),"0");
+        assert_eq!(number(8.66025),"8.66");
+        assert_eq!(number(-12.5),"-12.5");
+    }
+
+    #[test]
+    fn tiles_and_labels(){
+        let map : Map<Dummy,Bg> = Map::new((3,2),Position::new(0,0)).unwrap();
+        let svg = Svg::new(layout(Orientation::Flat)).labels(true)
+            .render(&map,|position,_,_| Style::new(if position.x == 0 { Color::BLACK } else { Color::rgba(255,128,0,51) }));
+        assert_eq!(svg.matches("<polygon").count(),6);
+        assert_eq!(svg.matches(r##"fill="#000000""##).count(),2);
+        assert_eq!(svg.matches(r##"fill="#ff8000" fill-opacity="0.2""##).count(),4);
+        assert!(svg.contains(">2,1</text>"));
+        assert!(!svg.contains("<marker"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn overlays_and_paths(){
+        let map : Map<Dummy,Bg> = Map::new((3,3),Position::new(0,0)).unwrap();
+        let path = vec![Position::new(0,0),Position::new(1,0),Position::new(1,1)];
+        let svg = Svg::new(layout(Orientation::Pointy))
+            .overlay(Position::new(2,2).in_range(1),Style::new(Color::TRANSPARENT).with_stroke(Color::rgb(255,0,0),2.0))
+            .path(path,Color::rgb(0,0,255))
+            .render(&map,|_,_,_| Style::new(Color::WHITE));
+        assert_eq!(svg.matches("<polygon").count(),9 + 7);
+        assert_eq!(svg.matches(r#"stroke-width="2""#).count(),7);
+        assert_eq!(svg.matches("<line").count(),2);
+        assert_eq!(svg.matches(r#"marker-end="url(#arrow-0)""#).count(),2);
+        assert!(!svg.contains("<text"));
+
+        // the picture is made bigger by the overlay going out of the map
+        let width = |svg:&str| svg.split('"').nth(3).unwrap().parse::<f64>().unwrap();
+        let plain = Svg::new(layout(Orientation::Pointy)).render(&map,|_,_,_| Style::new(Color::WHITE));
+        assert!(width(&svg) > width(&plain));
+        let margin = Svg::new(layout(Orientation::Pointy)).margin(5.0).render(&map,|_,_,_| Style::new(Color::WHITE));
+        assert!((width(&margin) - width(&plain) - 10.0).abs() < 0.02);
+    }
+}
@@ -2,16 +2,24 @@ use std::fmt;
 pub type Result<T> = ::std::result::Result<T,Error>;
 
 #[derive(Debug,Copy,Clone,Eq,PartialEq)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
 pub enum Reason {
     NegativeMapLength,
     OutOfRange,
     AlreadyOccupied,
     MissingTarget,
     ForbiddenLocation,
+    InvalidCoordinates,
+    InvalidTopology,
+    ConflictingOperations,
+    MissingCheckpoint,
+    InvalidFormat,
+    UnsupportedVersion,
     UnknownReason
 }
 
 #[derive(Debug,Copy,Clone,Eq,PartialEq)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
 pub struct Error {
     reason: Reason
 }
@@ -19,13 +27,15 @@ pub struct Error {
 impl Error {
     pub fn new(reason:Reason) -> Error {
         Error {
-            reason: reason
+            reason
         }
     }
-}
 
-impl ::std::error::Error for Error {
-    fn description(&self) -> &str {
+    pub fn reason(&self) -> Reason {
+        self.reason
+    }
+
+    fn message(&self) -> &'static str {
         match self.reason {
             Reason::NegativeMapLength => {
                 "map was created with a negative length"
@@ -42,6 +52,24 @@ impl ::std::error::Error for Error {
             Reason::ForbiddenLocation => {
                 "this position cannot allow contents to be inside"
             },
+            Reason::InvalidCoordinates => {
+                "these coordinates do not describe a valid position"
+            },
+            Reason::InvalidTopology => {
+                "this map shape cannot wrap around this way"
+            },
+            Reason::ConflictingOperations => {
+                "another operation already uses this position the same way"
+            },
+            Reason::MissingCheckpoint => {
+                "no checkpoint with this name is left in the history"
+            },
+            Reason::InvalidFormat => {
+                "this is not a valid encoding of a map"
+            },
+            Reason::UnsupportedVersion => {
+                "this version of the encoding cannot be read"
+            },
             Reason::UnknownReason => {
                 "this should never happen"
             },
@@ -49,8 +77,26 @@ impl ::std::error::Error for Error {
     }
 }
 
+impl ::std::error::Error for Error {
+    fn description(&self) -> &str {
+        self.message()
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "NegativeLengthError")
+        write!(f, "{}", self.message())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display(){
+        assert_eq!(Error::new(Reason::OutOfRange).to_string(),
+                   "want to access something that is not accessible");
+        assert_eq!(Error::new(Reason::MissingTarget).reason(),Reason::MissingTarget);
     }
 }
@@ -0,0 +1,241 @@
+//! Conversions between `Position`s and screen coordinates.
+//!
+//! Screen coordinates follow the usual convention of renderers: `x` grows to the right
+//! and `y` grows downwards. Hexes keep the axis convention of `Position`:
+//!
+//! * with `Orientation::Pointy`, `+x` (`E`) points right and `+y` (`NE`) up-right
+//! * with `Orientation::Flat`, `+x` (`E`) points up-right and `+y` (`NE`) straight up
+
+use std::ops::{Add,Sub,Mul};
+use pos::{Position,FractionalPosition,MainDirection};
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
+pub enum Orientation {
+    /// Hexes have a corner at the top, rows are horizontal
+    Pointy,
+    /// Hexes have an edge at the top, columns are vertical
+    Flat
+}
+
+impl Orientation {
+    /// Screen offset of the `(1,0)` and `(0,1)` positions for a hex of size 1
+    fn forward(self) -> [f64;4] {
+        let sqrt_3 = 3f64.sqrt();
+        match self {
+            Orientation::Pointy => [sqrt_3, sqrt_3 / 2.0, 0.0, -1.5],
+            Orientation::Flat => [1.5, 0.0, -sqrt_3 / 2.0, -sqrt_3],
+        }
+    }
+
+    /// Angle (in degrees, counter-clockwise on screen) of the first corner
+    fn start_angle(self) -> f64 {
+        match self {
+            Orientation::Pointy => 30.0,
+            Orientation::Flat => 0.0,
+        }
+    }
+}
+
+/// A point in screen coordinates
+#[derive(Copy,Clone,Debug,PartialEq,Default)]
+pub struct Point {
+    pub x : f64,
+    pub y : f64
+}
+
+impl Point {
+    pub fn new(x:f64,y:f64) -> Point {
+        Point {x,y}
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+    fn add(self, rhs:Point) -> Point {
+        Point::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+    fn sub(self, rhs:Point) -> Point {
+        Point::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl Mul<f64> for Point {
+    type Output = Point;
+    fn mul(self, rhs:f64) -> Point {
+        Point::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub struct Layout {
+    pub orientation : Orientation,
+    /// Distance between the center of a hex and its corners, horizontally and vertically.
+    /// Both are usually the same, unless hexes are stretched.
+    pub size : Point,
+    /// Screen coordinates of the center of `Position(0,0)`
+    pub origin : Point
+}
+
+impl Layout {
+    pub fn new(orientation:Orientation,size:Point,origin:Point) -> Layout {
+        Layout {orientation,size,origin}
+    }
+
+    /// Screen coordinates of the center of `position`
+    ///
+    /// ```
+    /// use hexgrid::pos::Position;
+    /// use hexgrid::layout::*;
+    /// let layout = Layout::new(Orientation::Pointy,Point::new(10.0,10.0),Point::new(0.0,0.0));
+    /// let p = layout.hex_to_pixel(Position::new(0,1));
+    /// assert!(p.x > 0.0 && p.y < 0.0);
+    /// ```
+    pub fn hex_to_pixel(&self,position:Position) -> Point {
+        self.fractional_hex_to_pixel(FractionalPosition::from(position))
+    }
+
+    pub fn fractional_hex_to_pixel(&self,position:FractionalPosition) -> Point {
+        let f = self.orientation.forward();
+        let x = (f[0] * position.x + f[1] * position.y) * self.size.x;
+        let y = (f[2] * position.x + f[3] * position.y) * self.size.y;
+        Point::new(x + self.origin.x, y + self.origin.y)
+    }
+
+    /// Inverse of `fractional_hex_to_pixel`
+    pub fn pixel_to_fractional_hex(&self,point:Point) -> FractionalPosition {
+        let f = self.orientation.forward();
+        let det = f[0] * f[3] - f[1] * f[2];
+        let x = (point.x - self.origin.x) / self.size.x;
+        let y = (point.y - self.origin.y) / self.size.y;
+        FractionalPosition::new((f[3] * x - f[1] * y) / det, (f[0] * y - f[2] * x) / det)
+    }
+
+    /// `Position` of the hex containing `point`
+    pub fn pixel_to_hex(&self,point:Point) -> Position {
+        self.pixel_to_fractional_hex(point).round()
+    }
+
+    /// Offset of the `i`-th corner relatively to the center of a hex
+    pub fn corner_offset(&self,i:usize) -> Point {
+        let angle = (self.orientation.start_angle() + 60.0 * (i % 6) as f64).to_radians();
+        Point::new(self.size.x * angle.cos(), - self.size.y * angle.sin())
+    }
+
+    /// Screen coordinates of the 6 corners of `position`.
+    ///
+    /// Corners are given counter-clockwise on screen, starting from the top-right one for
+    /// `Orientation::Pointy` and from the right one for `Orientation::Flat`.
+    pub fn hex_corners(&self,position:Position) -> [Point;6] {
+        let center = self.hex_to_pixel(position);
+        let mut corners = [center;6];
+        for (i,corner) in corners.iter_mut().enumerate() {
+            *corner = center + self.corner_offset(i);
+        }
+        corners
+    }
+
+    /// Screen coordinates of the middle of the edge shared by `position` and its neighbor
+    /// in `direction`.
+    pub fn edge_midpoint(&self,position:Position,direction:MainDirection) -> Point {
+        let center = self.hex_to_pixel(position);
+        let neighbor = self.hex_to_pixel(position + direction.to_pos());
+        center + (neighbor - center) * 0.5
+    }
+
+    /// Middle of the 6 edges of `position`, in the same order as `MainDirection`
+    /// (`NNE`, `E`, `SSE`, `SSW`, `W`, `NNW`).
+    pub fn edge_midpoints(&self,position:Position) -> [Point;6] {
+        use pos::MainDirection as M;
+        let directions = [M::NNE,M::E,M::SSE,M::SSW,M::W,M::NNW];
+        let mut midpoints = [Point::default();6];
+        for (midpoint,&direction) in midpoints.iter_mut().zip(directions.iter()) {
+            *midpoint = self.edge_midpoint(position,direction);
+        }
+        midpoints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pos::*;
+
+    fn assert_close(a:Point,b:Point) {
+        assert!((a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9,
+                "{:?} != {:?}",a,b);
+    }
+
+    fn layouts() -> Vec<Layout> {
+        vec![Layout::new(Orientation::Pointy,Point::new(10.0,10.0),Point::new(100.0,50.0)),
+             Layout::new(Orientation::Flat,Point::new(8.0,12.0),Point::new(-3.0,7.5))]
+    }
+
+    #[test]
+    fn pointy_axes(){
+        let layout = Layout::new(Orientation::Pointy,Point::new(1.0,1.0),Point::new(0.0,0.0));
+        let sqrt_3 = 3f64.sqrt();
+        assert_close(layout.hex_to_pixel(Position::new(0,0)),Point::new(0.0,0.0));
+        assert_close(layout.hex_to_pixel(E),Point::new(sqrt_3,0.0));
+        assert_close(layout.hex_to_pixel(NE),Point::new(sqrt_3 / 2.0,-1.5));
+        assert_close(layout.hex_to_pixel(SE),Point::new(sqrt_3 / 2.0,1.5));
+    }
+
+    #[test]
+    fn flat_axes(){
+        let layout = Layout::new(Orientation::Flat,Point::new(1.0,1.0),Point::new(0.0,0.0));
+        let sqrt_3 = 3f64.sqrt();
+        assert_close(layout.hex_to_pixel(E),Point::new(1.5,-sqrt_3 / 2.0));
+        assert_close(layout.hex_to_pixel(NE),Point::new(0.0,-sqrt_3));
+        assert_close(layout.hex_to_pixel(SE),Point::new(1.5,sqrt_3 / 2.0));
+    }
+
+    #[test]
+    fn round_trip(){
+        for layout in layouts() {
+            for position in Position::new(2,-3).iter_range(4) {
+                let center = layout.hex_to_pixel(position);
+                assert_eq!(layout.pixel_to_hex(center),position);
+                for corner in layout.hex_corners(position).iter() {
+                    // slightly inside the hex, towards its center
+                    let inside = *corner + (center - *corner) * 0.05;
+                    assert_eq!(layout.pixel_to_hex(inside),position);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn corners_are_shared(){
+        for layout in layouts() {
+            let corners = layout.hex_corners(Position::new(0,0));
+            let neighbor_corners = layout.hex_corners(NE);
+            let shared = corners.iter()
+                .filter(|a| neighbor_corners.iter().any(|b| (a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9))
+                .count();
+            assert_eq!(shared,2);
+        }
+    }
+
+    #[test]
+    fn edge_midpoints(){
+        for layout in layouts() {
+            let position = Position::new(1,1);
+            let corners = layout.hex_corners(position);
+            let midpoints = layout.edge_midpoints(position);
+            for midpoint in midpoints.iter() {
+                let on_edge = (0..6).any(|i| {
+                    let (a,b) = (corners[i],corners[(i + 1) % 6]);
+                    let middle = a + (b - a) * 0.5;
+                    (middle.x - midpoint.x).abs() < 1e-9 && (middle.y - midpoint.y).abs() < 1e-9
+                });
+                assert!(on_edge);
+            }
+            assert_close(midpoints[1],layout.edge_midpoint(position,MainDirection::E));
+        }
+    }
+}
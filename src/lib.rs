@@ -1,4 +1,14 @@
+#[cfg(feature="serde")]
+extern crate serde;
+#[cfg(feature="serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(all(test,feature="serde"))]
+extern crate serde_json;
+
 pub mod pos ;
 mod map ;
 pub mod error;
+pub mod layout;
+pub mod draw;
 pub use map::* ;
@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+use error::{Error,Reason,Result};
+use pos::{Position,OffsetKind};
+use map::{Map,MapShape,PositionAccessor,AllowContent};
+
+/// Rows and columns of the drawing of some positions, in `OffsetKind::OddR` coordinates
+struct Frame {
+    min_col:i32,
+    min_row:i32,
+    max_col:i32,
+    max_row:i32
+}
+
+impl Frame {
+    fn new<I>(positions:I) -> Frame where I : IntoIterator<Item=Position> {
+        let mut frame = Frame {min_col:i32::MAX,min_row:i32::MAX,max_col:i32::MIN,max_row:i32::MIN};
+        for position in positions {
+            let offset = position.to_offset(OffsetKind::OddR);
+            frame.min_col = frame.min_col.min(offset.col);
+            frame.min_row = frame.min_row.min(offset.row);
+            frame.max_col = frame.max_col.max(offset.col);
+            frame.max_row = frame.max_row.max(offset.row);
+        }
+        frame
+    }
+
+    fn lines(&self) -> usize {
+        (self.max_row - self.min_row + 1).max(0) as usize
+    }
+
+    fn line_len(&self) -> usize {
+        (2 * (self.max_col - self.min_col + 1)).max(0) as usize
+    }
+
+    /// Line and character of the glyph of `position`
+    fn cell(&self,position:Position) -> (usize,usize) {
+        let offset = position.to_offset(OffsetKind::OddR);
+        let line = (offset.row - self.min_row) as usize;
+        (line,(2 * (offset.col - self.min_col) + (offset.row & 1)) as usize)
+    }
+}
+
+impl<T,Bg> Map<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    /// Draws the map as text, one glyph per position.
+    ///
+    /// Rows of hexes are lines of text, as in `OffsetKind::OddR` coordinates: glyphs are
+    /// separated by a space, and odd rows are shifted right by one character. Lines are
+    /// ended by `'\n'` and have no trailing spaces.
+    ///
+    /// ```
+    /// use hexgrid::{Map,AllowContent,PositionAccessor};
+    /// use hexgrid::pos::Position;
+    /// # #[derive(Default)]
+    /// # struct Ground;
+    /// # impl AllowContent for Ground {
+    /// #     fn is_content_allowed(&self) -> bool { true }
+    /// # }
+    /// # struct Unit { position:Position }
+    /// # impl PositionAccessor for Unit {
+    /// #     fn set_position(&mut self,new_position:Position) { self.position = new_position; }
+    /// #     fn get_position(&self) -> Position { self.position }
+    /// # }
+    /// let mut map : Map<Unit,Ground> = Map::new((3,2),Position::new(0,0)).unwrap();
+    /// map.create_content(Position::new(1,1),Unit {position:Position::new(0,0)}).unwrap();
+    /// let text = map.to_ascii(|content,_| if content.is_some() { '@' } else { '.' });
+    /// assert_eq!(text," . @ .\n. . .\n");
+    /// ```
+    pub fn to_ascii<F>(&self,mut glyph:F) -> String where F : FnMut(Option<&T>,&Bg) -> char {
+        let frame = Frame::new(self.iter_bg().map(|(position,_)| position));
+        let mut lines = vec![vec![' ';frame.line_len()];frame.lines()];
+        for (position,(content,bg)) in self.iter() {
+            let (line,character) = frame.cell(position);
+            lines[line][character] = glyph(content.as_ref(),bg);
+        }
+        let mut text = String::new();
+        for line in lines {
+            let line : String = line.into_iter().collect();
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Builds a map from a drawing made by `to_ascii`, giving the content and background
+    /// of every position from its glyph.
+    ///
+    /// Empty lines before the drawing are ignored, as are blank lines after it and
+    /// missing trailing spaces: the glyph of a missing character is `' '`. A first row
+    /// made only of `' '` glyphs must keep its spaces, up to its last glyph, to be told
+    /// apart from an empty line.
+    ///
+    /// # Errors
+    ///
+    /// * `NegativeMapLength` if a dimension of the shape is negative
+    /// * `InvalidFormat` if `tile` does not know a glyph, or if there is a glyph which is
+    ///   not at a position of the map
+    /// * `ForbiddenLocation` if a content is given with a background not allowing it
+    pub fn from_ascii<F>(text:&str,shape:MapShape,offset:Position,mut tile:F) -> Result<Map<T,Bg>>
+        where F : FnMut(char) -> Option<(Option<T>,Bg)> {
+        let mut map = Map::with_shape(shape,offset)?;
+        let positions : Vec<Position> = map.iter_bg().map(|(position,_)| position).collect();
+        let frame = Frame::new(positions.iter().cloned());
+        let mut lines : Vec<Vec<char>> = text.lines()
+            .skip_while(|line| line.is_empty())
+            .map(|line| line.chars().collect())
+            .collect();
+        while lines.last().is_some_and(|line| line.iter().all(|c| c.is_whitespace())) {
+            lines.pop();
+        }
+        let mut cells = HashSet::with_capacity(positions.len());
+        for position in positions {
+            let (line,character) = frame.cell(position);
+            let glyph = lines.get(line).and_then(|line| line.get(character)).cloned().unwrap_or(' ');
+            let (content,bg) = tile(glyph).ok_or_else(|| Error::new(Reason::InvalidFormat))?;
+            *map.get_bg_mut(position)? = bg;
+            if let Some(content) = content {
+                map.create_content(position,content)?;
+            }
+            cells.insert((line,character));
+        }
+        for (line,characters) in lines.iter().enumerate() {
+            for (character,c) in characters.iter().enumerate() {
+                if !c.is_whitespace() && !cells.contains(&(line,character)) {
+                    return Err(Error::new(Reason::InvalidFormat));
+                }
+            }
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::tests::{Dummy,Bg};
+
+    fn glyph(content:Option<&Dummy>,bg:&Bg) -> char {
+        match (content,bg.kind.as_str()) {
+            (Some(_),_) => '@',
+            (None,"Obstacle") => '#',
+            (None,_) => '.',
+        }
+    }
+
+    fn tile(glyph:char) -> Option<(Option<Dummy>,Bg)> {
+        let dummy = Dummy {pos:Position::default(),name:String::from("dummy")};
+        match glyph {
+            '@' => Some((Some(dummy),Bg::default())),
+            '#' => Some((None,Bg {kind:String::from("Obstacle")})),
+            '.' => Some((None,Bg::default())),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn render(){
+        let mut map : Map<Dummy,Bg> = Map::with_shape(MapShape::Hexagon {radius:1},Position::new(0,0)).unwrap();
+        map.get_bg_mut(Position::new(1,0)).unwrap().kind = String::from("Obstacle");
+        map.create_content(Position::new(0,0),Dummy {pos:Position::default(),name:String::from("a")}).unwrap();
+        assert_eq!(map.to_ascii(glyph)," . .\n. @ #\n . .\n");
+    }
+
+    #[test]
+    fn parse(){
+        let text = "
+  . . . #
+ . @ . .
+. . # .
+";
+        let shape = MapShape::Parallelogram {width:4,height:3};
+        let map : Map<Dummy,Bg> = Map::from_ascii(text,shape,Position::new(-2,0),tile).unwrap();
+        assert_eq!(map.get_bg(Position::new(1,2)).unwrap().kind,"Obstacle");
+        assert_eq!(map.get_bg(Position::new(0,0)).unwrap().kind,"Obstacle");
+        assert_eq!(map.get_contents(Position::new(-1,1)).unwrap().as_ref().map(|d| d.pos),Some(Position::new(-1,1)));
+        assert_eq!(map.to_ascii(glyph),text.trim_start_matches('\n'));
+
+        let stray = "  . . . # x\n . @ . .\n. . # .";
+        assert_eq!(Map::<Dummy,Bg>::from_ascii(stray,shape,Position::new(-2,0),tile).err(),Some(Error::new(Reason::InvalidFormat)));
+        let unknown = "  . . . ?\n . @ . .\n. . # .";
+        assert_eq!(Map::<Dummy,Bg>::from_ascii(unknown,shape,Position::new(-2,0),tile).err(),Some(Error::new(Reason::InvalidFormat)));
+        let missing = "  . . . #\n . @ . .\n";
+        assert_eq!(Map::<Dummy,Bg>::from_ascii(missing,shape,Position::new(-2,0),tile).err(),Some(Error::new(Reason::InvalidFormat)));
+    }
+
+    #[test]
+    fn blank_first_row(){
+        // spaces are plain ground
+        let tile = |glyph| if glyph == ' ' { Some((None,Bg::default())) } else { tile(glyph) };
+        let shape = MapShape::Parallelogram {width:2,height:2};
+        let map : Map<Dummy,Bg> = Map::from_ascii("\n    \n# .",shape,Position::new(0,0),tile).unwrap();
+        assert_eq!(map.to_ascii(glyph)," . .\n# .\n");
+    }
+}
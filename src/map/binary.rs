@@ -0,0 +1,607 @@
+use error::{Error,Reason,Result};
+use pos::Position;
+use layout::Orientation;
+use map::{Map,MapShape,Wrap,PositionAccessor,AllowContent};
+
+const MAGIC : &[u8] = b"HXGM";
+
+/// Most positions a decoded map can have, so that a forged header cannot make
+/// `Map::from_bytes` allocate without bounds
+const MAX_LENGTH : usize = 1 << 24;
+
+/// Version of the binary encoding of `Map`, see `Map::to_bytes`.
+///
+/// Every number is little-endian. A map is written as:
+///
+/// | size | field                                                                 |
+/// |------|-----------------------------------------------------------------------|
+/// | 4    | magic, `b"HXGM"`                                                      |
+/// | 2    | format version, `MAP_FORMAT_VERSION`                                  |
+/// | 2    | `Codec::VERSION` of the backgrounds                                   |
+/// | 2    | `Codec::VERSION` of the contents                                      |
+/// | 1    | shape: `0` parallelogram, `1` hexagon, `2` rectangle, `3` triangle    |
+/// | 1    | orientation of a rectangle (`0` pointy, `1` flat), `0` otherwise      |
+/// | 4+4  | `width` and `height`, or `radius` / `size` then `0`                   |
+/// | 4    | `length`, the number of positions of the shape                        |
+/// | 4+4  | `x` and `y` of the offset                                             |
+/// | 1    | wrap: `0` none, `1` horizontal, `2` both                              |
+/// | 4    | number of background runs                                             |
+/// | ...  | runs: `4` bytes of run length, then the background                    |
+/// | 4    | number of contents                                                    |
+/// | ...  | contents by increasing index: `4` bytes of index, then the content    |
+///
+/// Runs are made of consecutive positions whose backgrounds encode to the same bytes, and
+/// indexes are the ones of `MapShape::pos_to_index`. Backgrounds and contents are written by
+/// their `Codec`, and read back by `Codec::migrate` when they were written by an older
+/// `Codec::VERSION`.
+pub const MAP_FORMAT_VERSION : u16 = 1;
+
+/// Bytes being written by `Codec::encode`.
+#[derive(Clone,Debug,Default)]
+pub struct Encoder {
+    bytes:Vec<u8>
+}
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn write_bytes(&mut self,bytes:&[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_u8(&mut self,value:u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_u16(&mut self,value:u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self,value:u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self,value:u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self,value:i32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i64(&mut self,value:i64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Bytes being read by `Codec::decode`.
+///
+/// Every read fails with `InvalidFormat` when too few bytes are left.
+#[derive(Clone,Debug)]
+pub struct Decoder<'a> {
+    bytes:&'a [u8]
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes:&'a [u8]) -> Decoder<'a> {
+        Decoder {
+            bytes
+        }
+    }
+
+    /// Number of bytes left to read
+    pub fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn read_bytes(&mut self,len:usize) -> Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            return Err(Error::new(Reason::InvalidFormat));
+        }
+        let (read,rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(read)
+    }
+
+    fn read_array<A>(&mut self,mut array:A) -> Result<A> where A : AsMut<[u8]> {
+        let len = array.as_mut().len();
+        array.as_mut().copy_from_slice(self.read_bytes(len)?);
+        Ok(array)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16> {
+        self.read_array([0;2]).map(u16::from_le_bytes)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        self.read_array([0;4]).map(u32::from_le_bytes)
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        self.read_array([0;8]).map(u64::from_le_bytes)
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32> {
+        self.read_array([0;4]).map(i32::from_le_bytes)
+    }
+
+    pub fn read_i64(&mut self) -> Result<i64> {
+        self.read_array([0;8]).map(i64::from_le_bytes)
+    }
+}
+
+/// Binary encoding of the backgrounds and contents of a `Map`, see `Map::to_bytes`.
+///
+/// `VERSION` is saved along with the map. Bump it when the encoding changes, and teach
+/// `migrate` to read the previous versions.
+///
+/// ```
+/// use hexgrid::{Codec,Encoder,Decoder};
+/// use hexgrid::error::{Error,Reason,Result};
+///
+/// struct Unit { hp:u32, shield:u32 }
+///
+/// impl Codec for Unit {
+///     // version 0 had no shield
+///     const VERSION : u16 = 1;
+///     fn encode(&self,encoder:&mut Encoder) {
+///         encoder.write_u32(self.hp);
+///         encoder.write_u32(self.shield);
+///     }
+///     fn decode(decoder:&mut Decoder) -> Result<Unit> {
+///         Ok(Unit { hp:decoder.read_u32()?, shield:decoder.read_u32()? })
+///     }
+///     fn migrate(version:u16,decoder:&mut Decoder) -> Result<Unit> {
+///         match version {
+///             0 => Ok(Unit { hp:decoder.read_u32()?, shield:0 }),
+///             _ => Err(Error::new(Reason::UnsupportedVersion))
+///         }
+///     }
+/// }
+/// ```
+pub trait Codec : Sized {
+    const VERSION : u16 = 0;
+
+    fn encode(&self,encoder:&mut Encoder);
+
+    /// Reads a value written by `encode` with the current `VERSION`.
+    fn decode(decoder:&mut Decoder) -> Result<Self>;
+
+    /// Reads a value written with an older `version`.
+    ///
+    /// # Errors
+    ///
+    /// * `UnsupportedVersion` by default
+    fn migrate(version:u16,decoder:&mut Decoder) -> Result<Self> {
+        let _ = (version,decoder);
+        Err(Error::new(Reason::UnsupportedVersion))
+    }
+}
+
+/// Reads a value written with `version` of its codec
+fn decode_version<C>(version:u16,decoder:&mut Decoder) -> Result<C> where C : Codec {
+    if version == C::VERSION {
+        C::decode(decoder)
+    } else if version < C::VERSION {
+        C::migrate(version,decoder)
+    } else {
+        Err(Error::new(Reason::UnsupportedVersion))
+    }
+}
+
+macro_rules! impl_codec_int {
+    ($($t:ty, $write:ident, $read:ident);*) => {
+        $(
+        impl Codec for $t {
+            fn encode(&self,encoder:&mut Encoder) {
+                encoder.$write(*self);
+            }
+
+            fn decode(decoder:&mut Decoder) -> Result<$t> {
+                decoder.$read()
+            }
+        }
+        )*
+    }
+}
+
+impl_codec_int!(u8,write_u8,read_u8; u16,write_u16,read_u16; u32,write_u32,read_u32;
+                u64,write_u64,read_u64; i32,write_i32,read_i32; i64,write_i64,read_i64);
+
+impl Codec for bool {
+    fn encode(&self,encoder:&mut Encoder) {
+        encoder.write_u8(*self as u8);
+    }
+
+    fn decode(decoder:&mut Decoder) -> Result<bool> {
+        match decoder.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::new(Reason::InvalidFormat))
+        }
+    }
+}
+
+impl Codec for String {
+    fn encode(&self,encoder:&mut Encoder) {
+        encoder.write_u32(self.len() as u32);
+        encoder.write_bytes(self.as_bytes());
+    }
+
+    fn decode(decoder:&mut Decoder) -> Result<String> {
+        let len = decoder.read_u32()? as usize;
+        let bytes = decoder.read_bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::new(Reason::InvalidFormat))
+    }
+}
+
+impl Codec for Position {
+    fn encode(&self,encoder:&mut Encoder) {
+        encoder.write_i32(self.x);
+        encoder.write_i32(self.y);
+    }
+
+    fn decode(decoder:&mut Decoder) -> Result<Position> {
+        Ok(Position::new(decoder.read_i32()?,decoder.read_i32()?))
+    }
+}
+
+impl<C> Codec for Option<C> where C : Codec {
+    const VERSION : u16 = C::VERSION;
+
+    fn encode(&self,encoder:&mut Encoder) {
+        match *self {
+            None => encoder.write_u8(0),
+            Some(ref value) => {
+                encoder.write_u8(1);
+                value.encode(encoder);
+            }
+        }
+    }
+
+    fn decode(decoder:&mut Decoder) -> Result<Option<C>> {
+        match decoder.read_u8()? {
+            0 => Ok(None),
+            1 => C::decode(decoder).map(Some),
+            _ => Err(Error::new(Reason::InvalidFormat))
+        }
+    }
+
+    fn migrate(version:u16,decoder:&mut Decoder) -> Result<Option<C>> {
+        match decoder.read_u8()? {
+            0 => Ok(None),
+            1 => C::migrate(version,decoder).map(Some),
+            _ => Err(Error::new(Reason::InvalidFormat))
+        }
+    }
+}
+
+fn encode_shape(shape:MapShape,encoder:&mut Encoder) {
+    let (tag,orientation,a,b) = match shape {
+        MapShape::Parallelogram {width,height} => (0,0,width,height),
+        MapShape::Hexagon {radius} => (1,0,radius,0),
+        MapShape::Rectangle {orientation:Orientation::Pointy,width,height} => (2,0,width,height),
+        MapShape::Rectangle {orientation:Orientation::Flat,width,height} => (2,1,width,height),
+        MapShape::Triangle {size} => (3,0,size,0),
+    };
+    encoder.write_u8(tag);
+    encoder.write_u8(orientation);
+    encoder.write_i32(a);
+    encoder.write_i32(b);
+}
+
+fn decode_shape(decoder:&mut Decoder) -> Result<MapShape> {
+    let (tag,orientation) = (decoder.read_u8()?,decoder.read_u8()?);
+    let (a,b) = (decoder.read_i32()?,decoder.read_i32()?);
+    let orientation = match orientation {
+        0 => Orientation::Pointy,
+        1 if tag == 2 => Orientation::Flat,
+        _ => return Err(Error::new(Reason::InvalidFormat))
+    };
+    match tag {
+        0 => Ok(MapShape::Parallelogram {width:a,height:b}),
+        1 => Ok(MapShape::Hexagon {radius:a}),
+        2 => Ok(MapShape::Rectangle {orientation,width:a,height:b}),
+        3 => Ok(MapShape::Triangle {size:a}),
+        _ => Err(Error::new(Reason::InvalidFormat))
+    }
+}
+
+fn decode_wrap(decoder:&mut Decoder) -> Result<Wrap> {
+    match decoder.read_u8()? {
+        0 => Ok(Wrap::None),
+        1 => Ok(Wrap::Horizontal),
+        2 => Ok(Wrap::Both),
+        _ => Err(Error::new(Reason::InvalidFormat))
+    }
+}
+
+/// Fields of the header following the format version
+struct Header {
+    bg_version:u16,
+    content_version:u16,
+    shape:MapShape,
+    length:u32,
+    offset:Position,
+    wrap:Wrap
+}
+
+/// Reads the header of any supported format version, which is where older format
+/// versions get migrated to the current `Header`
+fn decode_header(decoder:&mut Decoder) -> Result<Header> {
+    if decoder.read_bytes(MAGIC.len())? != MAGIC {
+        return Err(Error::new(Reason::InvalidFormat));
+    }
+    match decoder.read_u16()? {
+        1 => Ok(Header {
+            bg_version:decoder.read_u16()?,
+            content_version:decoder.read_u16()?,
+            shape:decode_shape(decoder)?,
+            length:decoder.read_u32()?,
+            offset:Position::decode(decoder)?,
+            wrap:decode_wrap(decoder)?
+        }),
+        _ => Err(Error::new(Reason::UnsupportedVersion))
+    }
+}
+
+impl<T,Bg> Map<T,Bg> where T : PositionAccessor + Codec, Bg : Default + AllowContent + Codec {
+    /// Encodes the map in a compact binary format, described with `MAP_FORMAT_VERSION`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder.write_bytes(MAGIC);
+        encoder.write_u16(MAP_FORMAT_VERSION);
+        encoder.write_u16(Bg::VERSION);
+        encoder.write_u16(T::VERSION);
+        encode_shape(self.shape,&mut encoder);
+        encoder.write_u32(self.bg_slice.len() as u32);
+        self.offset.encode(&mut encoder);
+        encoder.write_u8(match self.wrap {
+            Wrap::None => 0,
+            Wrap::Horizontal => 1,
+            Wrap::Both => 2,
+        });
+
+        let mut runs : Vec<(u32,Vec<u8>)> = Vec::new();
+        for bg in self.bg_slice.iter() {
+            let mut bg_encoder = Encoder::new();
+            bg.encode(&mut bg_encoder);
+            let bytes = bg_encoder.into_bytes();
+            match runs.last_mut() {
+                Some(&mut (ref mut count,ref last)) if *last == bytes => *count += 1,
+                _ => runs.push((1,bytes)),
+            }
+        }
+        encoder.write_u32(runs.len() as u32);
+        for (count,bytes) in runs {
+            encoder.write_u32(count);
+            encoder.write_bytes(&bytes);
+        }
+
+        let contents : Vec<(usize,&T)> = self.contents_slice.iter().enumerate()
+            .filter_map(|(index,content)| content.as_ref().map(|content| (index,content)))
+            .collect();
+        encoder.write_u32(contents.len() as u32);
+        for (index,content) in contents {
+            encoder.write_u32(index as u32);
+            content.encode(&mut encoder);
+        }
+        encoder.into_bytes()
+    }
+
+    /// Decodes a map written by `to_bytes`, possibly by an older format version or older
+    /// codecs. Contents are put back regardless of their background, like they were saved,
+    /// and get their position through `set_position`.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidFormat` if the bytes are not a map, are truncated or are followed by
+    ///   anything else, or if the shape has more than 2^24 positions
+    /// * `UnsupportedVersion` if the format or a codec is more recent than this one, or
+    ///   if a codec cannot migrate from an older version
+    /// * the errors of `Map::with_wrap` for the shape and wrap
+    pub fn from_bytes(bytes:&[u8]) -> Result<Map<T,Bg>> where Bg : Clone {
+        let mut decoder = Decoder::new(bytes);
+        let header = decode_header(&mut decoder)?;
+        header.shape.validate()?;
+        let length = match header.shape.checked_len() {
+            Some(length) if length == header.length as usize && length <= MAX_LENGTH => length,
+            _ => return Err(Error::new(Reason::InvalidFormat)),
+        };
+        let mut map = Map::with_wrap(header.shape,header.offset,header.wrap)?;
+
+        let mut bgs : Vec<Bg> = Vec::with_capacity(length);
+        for _ in 0 .. decoder.read_u32()? {
+            let count = decoder.read_u32()? as usize;
+            let bg : Bg = decode_version(header.bg_version,&mut decoder)?;
+            if count == 0 || count > length - bgs.len() {
+                return Err(Error::new(Reason::InvalidFormat));
+            }
+            bgs.extend((0 .. count).map(|_| bg.clone()));
+        }
+        if bgs.len() != length {
+            return Err(Error::new(Reason::InvalidFormat));
+        }
+        map.bg_slice = bgs.into_boxed_slice();
+
+        let mut next_index = 0;
+        for _ in 0 .. decoder.read_u32()? {
+            let index = decoder.read_u32()? as usize;
+            let mut content : T = decode_version(header.content_version,&mut decoder)?;
+            if index < next_index || index >= length {
+                return Err(Error::new(Reason::InvalidFormat));
+            }
+            content.set_position(map.index_to_pos(index)?);
+            map.contents_slice[index] = Some(content);
+            next_index = index + 1;
+        }
+        if decoder.remaining() != 0 {
+            return Err(Error::new(Reason::InvalidFormat));
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::tests::{Dummy,Bg};
+
+    impl Codec for Dummy {
+        const VERSION : u16 = 2;
+
+        fn encode(&self,encoder:&mut Encoder) {
+            self.name.encode(encoder);
+        }
+
+        fn decode(decoder:&mut Decoder) -> Result<Dummy> {
+            Ok(Dummy {pos:Position::default(),name:String::decode(decoder)?})
+        }
+
+        /// Version 1 stored the name as a single byte of length
+        fn migrate(version:u16,decoder:&mut Decoder) -> Result<Dummy> {
+            if version != 1 {
+                return Err(Error::new(Reason::UnsupportedVersion));
+            }
+            let len = decoder.read_u8()? as usize;
+            let name = String::from_utf8(decoder.read_bytes(len)?.to_vec()).map_err(|_| Error::new(Reason::InvalidFormat))?;
+            Ok(Dummy {pos:Position::default(),name})
+        }
+    }
+
+    impl Codec for Bg {
+        fn encode(&self,encoder:&mut Encoder) {
+            self.kind.encode(encoder);
+        }
+
+        fn decode(decoder:&mut Decoder) -> Result<Bg> {
+            Ok(Bg {kind:String::decode(decoder)?})
+        }
+    }
+
+    fn dummy(name:&str) -> Dummy {
+        Dummy {pos:Position::default(),name:String::from(name)}
+    }
+
+    fn sample() -> Map<Dummy,Bg> {
+        let mut map : Map<Dummy,Bg> = Map::with_wrap(MapShape::Rectangle {orientation:Orientation::Pointy,width:4,height:3},
+                                                     Position::new(2,-1),Wrap::Horizontal).unwrap();
+        map.get_bg_mut(Position::new(3,-1)).unwrap().kind = String::from("Obstacle");
+        map.create_content(Position::new(2,-1),dummy("first")).unwrap();
+        map.create_content(Position::new(5,-1),dummy("second")).unwrap();
+        map
+    }
+
+    #[test]
+    fn round_trip(){
+        let map = sample();
+        let bytes = map.to_bytes();
+        assert_eq!(&bytes[0 .. 4],b"HXGM");
+        let loaded : Map<Dummy,Bg> = Map::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.shape(),map.shape());
+        assert_eq!(loaded.offset(),map.offset());
+        assert_eq!(loaded.wrap(),map.wrap());
+        for ((position,bg),(_,expected)) in loaded.iter_bg().zip(map.iter_bg()) {
+            assert_eq!(bg.kind,expected.kind,"{:?}",position);
+        }
+        for ((position,content),(_,expected)) in loaded.iter_contents().zip(map.iter_contents()) {
+            assert_eq!(content.as_ref().map(|d| (d.pos,&d.name)),expected.as_ref().map(|d| (position,&d.name)));
+        }
+        for &shape in &[MapShape::Hexagon {radius:2},MapShape::Triangle {size:3},
+                        MapShape::Rectangle {orientation:Orientation::Flat,width:2,height:5}] {
+            let map : Map<Dummy,Bg> = Map::with_shape(shape,Position::new(1,1)).unwrap();
+            assert_eq!(Map::<Dummy,Bg>::from_bytes(&map.to_bytes()).unwrap().shape(),shape);
+        }
+    }
+
+    /// Size of the header, up to the wrap
+    const HEADER : usize = 33;
+
+    #[test]
+    fn run_length(){
+        let map : Map<Dummy,Bg> = Map::new((50,50),Position::new(0,0)).unwrap();
+        // a single run of empty kinds, then no content
+        assert_eq!(map.to_bytes().len(),HEADER + 4 + 4 + 4 + 4);
+        let bytes = sample().to_bytes();
+        assert_eq!(&bytes[HEADER .. HEADER + 4],&3u32.to_le_bytes());
+    }
+
+    #[test]
+    fn migration(){
+        let mut bytes = sample().to_bytes();
+        // rewrite the contents the way version 1 of the codec did
+        let contents = bytes.len() - (4 + (4 + 4 + 5) + (4 + 4 + 6));
+        bytes.truncate(contents);
+        bytes[8 .. 10].copy_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&[3,b'o',b'l',b'd']);
+        let loaded : Map<Dummy,Bg> = Map::from_bytes(&bytes).unwrap();
+        let content = loaded.get_contents(Position::new(2,-1)).unwrap().as_ref().unwrap();
+        assert_eq!((content.pos,content.name.as_str()),(Position::new(2,-1),"old"));
+
+        bytes[8 .. 10].copy_from_slice(&3u16.to_le_bytes());
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(&bytes).err(),Some(Error::new(Reason::UnsupportedVersion)));
+        bytes[8 .. 10].copy_from_slice(&0u16.to_le_bytes());
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(&bytes).err(),Some(Error::new(Reason::UnsupportedVersion)));
+        bytes[4 .. 6].copy_from_slice(&2u16.to_le_bytes());
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(&bytes).err(),Some(Error::new(Reason::UnsupportedVersion)));
+    }
+
+    #[test]
+    fn invalid(){
+        let bytes = sample().to_bytes();
+        let invalid = Some(Error::new(Reason::InvalidFormat));
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(&bytes[.. bytes.len() - 1]).err(),invalid);
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(b"HXGN").err(),invalid);
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(&longer).err(),invalid);
+        let mut wrong_length = bytes.clone();
+        wrong_length[20] += 1;
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(&wrong_length).err(),invalid);
+    }
+
+    #[test]
+    fn malformed_header(){
+        let header = |shape:MapShape,length:u32| {
+            let mut encoder = Encoder::new();
+            encoder.write_bytes(MAGIC);
+            encoder.write_u16(MAP_FORMAT_VERSION);
+            encoder.write_u16(0);
+            encoder.write_u16(2);
+            encode_shape(shape,&mut encoder);
+            encoder.write_u32(length);
+            Position::new(0,0).encode(&mut encoder);
+            encoder.write_u8(0);
+            encoder.into_bytes()
+        };
+        let invalid = Some(Error::new(Reason::InvalidFormat));
+        let huge = MapShape::Parallelogram {width:i32::MAX,height:i32::MAX};
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(&header(huge,u32::MAX)).err(),invalid);
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(&header(MapShape::Hexagon {radius:i32::MAX},u32::MAX)).err(),invalid);
+        // consistent with its length, but too big
+        let big = MapShape::Parallelogram {width:1 << 13,height:1 << 12};
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(&header(big,1 << 25)).err(),invalid);
+        assert_eq!(Map::<Dummy,Bg>::from_bytes(&header(MapShape::Triangle {size:-1},0)).err(),
+                   Some(Error::new(Reason::NegativeMapLength)));
+    }
+}
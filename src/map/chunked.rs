@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::collections::hash_map;
+use error::{Error,Reason,Result};
+use pos::Position;
+use map::{Map,MapShape,HexMap,HexMapIter,PositionAccessor,AllowContent,PositionStatus};
+
+/// A map without bounds, made of chunks of `chunk_size` by `chunk_size` positions (see
+/// `MapShape::Parallelogram`) which are only allocated once something is written in them.
+///
+/// Positions of chunks which are not loaded have no content and a default `Bg`. Chunks
+/// are identified by their coordinates: the chunk `(i,j)` holds the positions from
+/// `(i * chunk_size, j * chunk_size)` included to `((i+1) * chunk_size, (j+1) * chunk_size)`
+/// excluded.
+pub struct ChunkedMap<T : PositionAccessor,Bg : Default + AllowContent> {
+    chunks : HashMap<Position,Map<T,Bg>>,
+    chunk_size : i32,
+    /// returned when reading a position of an unloaded chunk
+    empty_content : Option<T>,
+    default_bg : Bg,
+    search_limit : usize
+}
+
+/// Positions a search expands at most on a new `ChunkedMap`, see `HexMap::search_limit`
+pub const DEFAULT_SEARCH_LIMIT : usize = 1 << 16;
+
+impl<T,Bg> ChunkedMap<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    /// # Errors
+    ///
+    /// * `NegativeMapLength` if `chunk_size` is not strictly positive
+    pub fn new(chunk_size:i32) -> Result<ChunkedMap<T,Bg>> {
+        if chunk_size <= 0 {
+            Err(Error::new(Reason::NegativeMapLength))
+        } else {
+            Ok(ChunkedMap {
+                chunks:HashMap::new(),
+                chunk_size,
+                empty_content:None,
+                default_bg:Bg::default(),
+                search_limit:DEFAULT_SEARCH_LIMIT
+            })
+        }
+    }
+
+    pub fn chunk_size(&self) -> i32 {
+        self.chunk_size
+    }
+
+    /// Coordinates of the chunk holding `position`
+    pub fn chunk_of(&self,position:Position) -> Position {
+        Position::new(position.x.div_euclid(self.chunk_size),position.y.div_euclid(self.chunk_size))
+    }
+
+    /// Most positions `find_path` and `reachable` expand before giving up, since the
+    /// map has no edge to stop them; `DEFAULT_SEARCH_LIMIT` unless changed.
+    pub fn search_limit(&self) -> usize {
+        self.search_limit
+    }
+
+    pub fn set_search_limit(&mut self,search_limit:usize) {
+        self.search_limit = search_limit;
+    }
+
+    pub fn is_loaded(&self,chunk:Position) -> bool {
+        self.chunks.contains_key(&chunk)
+    }
+
+    pub fn loaded_chunks_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Every loaded chunk with its coordinates, in no particular order
+    pub fn iter_chunks(&self) -> hash_map::Iter<'_,Position,Map<T,Bg>> {
+        self.chunks.iter()
+    }
+
+    pub fn iter_chunks_mut(&mut self) -> hash_map::IterMut<'_,Position,Map<T,Bg>> {
+        self.chunks.iter_mut()
+    }
+
+    /// Every position of the loaded chunks with its content, chunk by chunk
+    pub fn iter_contents(&self) -> impl Iterator<Item=(Position,&Option<T>)> {
+        self.chunks.values().flat_map(|chunk| chunk.iter_contents())
+    }
+
+    /// Loads the chunk holding `position` if needed, and returns it.
+    pub fn load_chunk(&mut self,position:Position) -> &mut Map<T,Bg> {
+        let chunk = self.chunk_of(position);
+        let chunk_size = self.chunk_size;
+        self.chunks.entry(chunk).or_insert_with(|| {
+            let shape = MapShape::Parallelogram {width:chunk_size,height:chunk_size};
+            Map::with_shape(shape,chunk * chunk_size).expect("chunk_size is positive")
+        })
+    }
+
+    /// Drops the chunk at `chunk` (in chunk coordinates) and everything in it.
+    pub fn unload_chunk(&mut self,chunk:Position) -> Option<Map<T,Bg>> {
+        self.chunks.remove(&chunk)
+    }
+
+    /// Drops every chunk holding no content and only default `Bg`s, which reads the same
+    /// once unloaded, and returns how many were dropped.
+    pub fn unload_empty_chunks(&mut self) -> usize where Bg : PartialEq {
+        let default_bg = Bg::default();
+        self.unload_chunks_where(|_,chunk| {
+            chunk.iter_contents().all(|(_,content)| content.is_none())
+                && chunk.iter_bg().all(|(_,bg)| *bg == default_bg)
+        })
+    }
+
+    /// Drops every chunk for which `unload` returns `true`, given its coordinates and the
+    /// chunk itself, and returns how many were dropped. Everything in those chunks is lost.
+    pub fn unload_chunks_where<F>(&mut self,mut unload:F) -> usize where F : FnMut(Position,&Map<T,Bg>) -> bool {
+        let before = self.chunks.len();
+        self.chunks.retain(|&coordinates,chunk| !unload(coordinates,chunk));
+        before - self.chunks.len()
+    }
+
+    fn chunk(&self,position:Position) -> Option<&Map<T,Bg>> {
+        self.chunks.get(&self.chunk_of(position))
+    }
+
+    pub fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        match self.chunk(position) {
+            Some(chunk) => chunk.position_status(position),
+            None if self.default_bg.is_content_allowed() => Ok(PositionStatus::Empty),
+            None => Ok(PositionStatus::Forbidden),
+        }
+    }
+
+    /// Never fails, the `Result` is only there to keep the same API as `Map`.
+    pub fn get(&self,position:Position) -> Result<(&Option<T>,&Bg)> {
+        match self.chunk(position) {
+            Some(chunk) => chunk.get(position),
+            None => Ok((&self.empty_content,&self.default_bg)),
+        }
+    }
+
+    /// Loads the chunk of `position` if needed.
+    pub fn get_mut(&mut self,position:Position) -> Result<(&mut Option<T>,&mut Bg)> {
+        self.load_chunk(position).get_mut(position)
+    }
+
+    pub fn get_contents(&self,position:Position) -> Result<&Option<T>> {
+        self.get(position).map(|(content,_)| content)
+    }
+
+    /// Loads the chunk of `position` if needed.
+    pub fn get_contents_mut(&mut self,position:Position) -> Result<&mut Option<T>> {
+        self.load_chunk(position).get_contents_mut(position)
+    }
+
+    pub fn get_bg(&self,position:Position) -> Result<&Bg> {
+        self.get(position).map(|(_,bg)| bg)
+    }
+
+    /// Loads the chunk of `position` if needed.
+    pub fn get_bg_mut(&mut self,position:Position) -> Result<&mut Bg> {
+        self.load_chunk(position).get_bg_mut(position)
+    }
+
+    /// Replace a `Position` with a new content.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingTarget` if Position has no content (`None`)
+    pub fn replace_content(&mut self,position:Position,new_content:T) -> Result<T> {
+        if self.chunk(position).is_none() {
+            return Err(Error::new(Reason::MissingTarget));
+        }
+        self.load_chunk(position).replace_content(position,new_content)
+    }
+
+    /// Extract a content at `Position` and replace it with `None`.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingTarget` if Position has no content (`None`)
+    pub fn extract_content(&mut self,position:Position) -> Result<T> {
+        if self.chunk(position).is_none() {
+            return Err(Error::new(Reason::MissingTarget));
+        }
+        self.load_chunk(position).extract_content(position)
+    }
+
+    /// Put a new content at `Position`, loading its chunk if needed.
+    ///
+    /// # Errors
+    ///
+    /// * `AlreadyOccupied` if Position is busy
+    /// * `ForbiddenLocation` if the `Bg` of Position does not allow contents
+    pub fn create_content(&mut self,position:Position,new_content:T) -> Result<()> {
+        self.load_chunk(position).create_content(position,new_content)
+    }
+
+    /// Swap 2 elements.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingTarget` if one the 2 position has no content (`None`)
+    pub fn swap_contents(&mut self,pos_1:Position,pos_2:Position) -> Result<()> {
+        if self.get_contents(pos_1)?.is_none() || self.get_contents(pos_2)?.is_none() {
+            return Err(Error::new(Reason::MissingTarget));
+        }
+        if self.chunk_of(pos_1) == self.chunk_of(pos_2) {
+            return self.load_chunk(pos_1).swap_contents(pos_1,pos_2);
+        }
+        let mut content_1 = self.load_chunk(pos_1).extract_content(pos_1)?;
+        let mut content_2 = self.load_chunk(pos_2).extract_content(pos_2)?;
+        content_1.set_position(pos_2);
+        content_2.set_position(pos_1);
+        *self.load_chunk(pos_1).get_contents_mut(pos_1)? = Some(content_2);
+        *self.load_chunk(pos_2).get_contents_mut(pos_2)? = Some(content_1);
+        Ok(())
+    }
+
+    /// Move an element from a position to another, loading the chunk of `to` if needed.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingTarget` if the initial position has no element
+    /// * `AlreadyOccupied` if the final position is busy
+    /// * `ForbiddenLocation` if the `Bg` of the final position does not allow contents
+    pub fn move_contents(&mut self,from:Position,to:Position) -> Result<()> {
+        match self.position_status(to)? {
+            PositionStatus::Empty => (),
+            PositionStatus::Busy => return Err(Error::new(Reason::AlreadyOccupied)),
+            PositionStatus::Forbidden => return Err(Error::new(Reason::ForbiddenLocation)),
+        }
+        if self.get_contents(from)?.is_none() {
+            return Err(Error::new(Reason::MissingTarget));
+        }
+        if self.chunk_of(from) == self.chunk_of(to) {
+            return self.load_chunk(from).move_contents(from,to);
+        }
+        let content = self.load_chunk(from).extract_content(from)?;
+        self.load_chunk(to).create_content(to,content)
+    }
+}
+
+impl<T,Bg> HexMap for ChunkedMap<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    type Content = T ;
+    type Bg = Bg ;
+
+    fn get(&self,position:Position) -> Result<(Option<&T>,&Bg)> {
+        ChunkedMap::get(self,position).map(|(content,bg)| (content.as_ref(),bg))
+    }
+
+    /// Loads the chunk of `position` if needed.
+    fn get_mut(&mut self,position:Position) -> Result<(Option<&mut T>,&mut Bg)> {
+        ChunkedMap::get_mut(self,position).map(|(content,bg)| (content.as_mut(),bg))
+    }
+
+    /// Always true, every position is part of the map.
+    fn contains(&self,_position:Position) -> bool {
+        true
+    }
+
+    /// Positions of the loaded chunks.
+    fn iter<'a>(&'a self) -> HexMapIter<'a,T,Bg> {
+        Box::new(self.chunks.values().flat_map(|chunk| HexMap::iter(chunk)))
+    }
+
+    fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        ChunkedMap::position_status(self,position)
+    }
+
+    fn search_limit(&self) -> Option<usize> {
+        Some(self.search_limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::BusyPolicy;
+    use map::tests::{Dummy,Bg};
+    use pos::Position;
+
+    fn dummy(name:&str) -> Dummy {
+        Dummy {pos:Position::default(),name:String::from(name)}
+    }
+
+    #[test]
+    fn chunks(){
+        let map : ChunkedMap<Dummy,Bg> = ChunkedMap::new(8).unwrap();
+        assert_eq!(map.chunk_of(Position::new(0,0)),Position::new(0,0));
+        assert_eq!(map.chunk_of(Position::new(7,-1)),Position::new(0,-1));
+        assert_eq!(map.chunk_of(Position::new(-8,17)),Position::new(-1,2));
+        assert_eq!(map.chunk_of(Position::new(-9,16)),Position::new(-2,2));
+        assert!(ChunkedMap::<Dummy,Bg>::new(0).is_err());
+    }
+
+    #[test]
+    fn load_on_demand(){
+        let mut map : ChunkedMap<Dummy,Bg> = ChunkedMap::new(4).unwrap();
+        let far = Position::new(-1000,4000);
+        assert!(map.get_contents(far).unwrap().is_none());
+        assert_eq!(map.loaded_chunks_count(),0);
+        map.create_content(far,dummy("far")).unwrap();
+        assert_eq!(map.loaded_chunks_count(),1);
+        assert_eq!(map.get_contents(far).unwrap().as_ref().unwrap().get_position(),far);
+        assert_eq!(map.create_content(far,dummy("again")).unwrap_err(),
+                   Error::new(Reason::AlreadyOccupied));
+        assert_eq!(map.extract_content(Position::new(0,0)).unwrap_err(),
+                   Error::new(Reason::MissingTarget));
+        assert_eq!(map.loaded_chunks_count(),1);
+    }
+
+    #[test]
+    fn move_and_swap_across_chunks(){
+        let mut map : ChunkedMap<Dummy,Bg> = ChunkedMap::new(4).unwrap();
+        let (a,b,c) = (Position::new(3,3),Position::new(4,3),Position::new(-20,-20));
+        map.create_content(a,dummy("a")).unwrap();
+        map.move_contents(a,b).unwrap();
+        assert!(map.get_contents(a).unwrap().is_none());
+        assert_eq!(map.get_contents(b).unwrap().as_ref().unwrap().get_position(),b);
+        map.create_content(c,dummy("c")).unwrap();
+        map.swap_contents(b,c).unwrap();
+        assert_eq!(map.get_contents(b).unwrap().as_ref().unwrap().name,"c");
+        assert_eq!(map.get_contents(c).unwrap().as_ref().unwrap().name,"a");
+        assert_eq!(map.get_contents(b).unwrap().as_ref().unwrap().get_position(),b);
+        assert_eq!(map.get_contents(c).unwrap().as_ref().unwrap().get_position(),c);
+        assert_eq!(map.move_contents(b,c).unwrap_err(),Error::new(Reason::AlreadyOccupied));
+        map.get_bg_mut(Position::new(100,100)).unwrap().kind = String::from("Obstacle");
+        assert_eq!(map.move_contents(b,Position::new(100,100)).unwrap_err(),
+                   Error::new(Reason::ForbiddenLocation));
+        assert_eq!(map.swap_contents(b,Position::new(50,50)).unwrap_err(),
+                   Error::new(Reason::MissingTarget));
+    }
+
+    #[test]
+    fn unload(){
+        let mut map : ChunkedMap<Dummy,Bg> = ChunkedMap::new(4).unwrap();
+        map.create_content(Position::new(0,0),dummy("a")).unwrap();
+        map.create_content(Position::new(10,0),dummy("b")).unwrap();
+        map.get_bg_mut(Position::new(-10,0)).unwrap();
+        assert_eq!(map.loaded_chunks_count(),3);
+        map.extract_content(Position::new(10,0)).unwrap();
+        assert_eq!(map.unload_empty_chunks(),2);
+        assert_eq!(map.loaded_chunks_count(),1);
+        let chunks : Vec<Position> = map.iter_chunks().map(|(&chunk,_)| chunk).collect();
+        assert_eq!(chunks,vec![Position::new(0,0)]);
+        assert_eq!(map.iter_contents().filter(|(_,content)| content.is_some()).count(),1);
+
+        // a wall painted where there is no content is kept
+        map.get_bg_mut(Position::new(20,20)).unwrap().kind = String::from("Obstacle");
+        assert_eq!(map.unload_empty_chunks(),0);
+        assert_eq!(map.get_bg(Position::new(20,20)).unwrap().kind,"Obstacle");
+        assert_eq!(map.unload_chunks_where(|chunk,_| chunk == Position::new(5,5)),1);
+        assert_eq!(map.get_bg(Position::new(20,20)).unwrap().kind,"");
+        assert_eq!(map.loaded_chunks_count(),1);
+    }
+
+    #[test]
+    fn hex_map(){
+        let mut map : ChunkedMap<Dummy,Bg> = ChunkedMap::new(4).unwrap();
+        for y in -10..10 {
+            map.get_bg_mut(Position::new(0,y)).unwrap().kind = String::from("Obstacle");
+        }
+        let (path,cost) = map.find_path(Position::new(-2,0),Position::new(2,0),BusyPolicy::Blocked,|_| Some(1)).unwrap();
+        assert_eq!(cost as usize,path.len() - 1);
+        assert!(cost > 4);
+        assert!(path.iter().all(|position| position.x != 0 || position.y < -10 || position.y >= 10));
+        let visible = map.field_of_view(Position::new(-2,0),4,|bg| bg.kind == "Obstacle").unwrap();
+        assert!(!visible.contains(&Position::new(2,0)));
+        assert!(visible.contains(&Position::new(0,0)));
+        assert_eq!(HexMap::iter(&map).count(),map.loaded_chunks_count() * 16);
+    }
+
+    #[test]
+    fn enclosed_goal(){
+        let mut map : ChunkedMap<Dummy,Bg> = ChunkedMap::new(4).unwrap();
+        let goal = Position::new(10,10);
+        for position in goal.ring(1) {
+            map.get_bg_mut(position).unwrap().kind = String::from("Obstacle");
+        }
+        map.set_search_limit(2000);
+        assert_eq!(map.find_path(Position::new(0,0),goal,BusyPolicy::Blocked,|_| Some(1)),None);
+        let reachable = map.reachable(Position::new(0,0),1000,BusyPolicy::Blocked,|_| Some(1)).unwrap();
+        assert_eq!(reachable.len(),2000);
+        for &position in reachable.positions() {
+            assert!(reachable.path_to(position).unwrap().iter().all(|&step| reachable.contains(step)));
+        }
+        // the search still finds what is within the limit
+        assert_eq!(map.find_path(Position::new(0,0),Position::new(10,12),BusyPolicy::Blocked,|_| Some(1)).unwrap().1,22);
+    }
+}
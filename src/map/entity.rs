@@ -0,0 +1,386 @@
+use error::{Error,Reason,Result};
+use pos::Position;
+use map::{Map,MapShape,MapIter,Wrap,PositionAccessor,AllowContent,PositionStatus};
+use std::slice::{Iter,IterMut};
+
+/// Identifies a content of an `EntityMap` for as long as it stays in the map.
+///
+/// Once its content is extracted, a handle stays invalid even
+/// if a new content takes its slot (until the slot has been reused 2^32 times).
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+pub struct Handle {
+    index : u32,
+    generation : u32
+}
+
+/// What the grid of an `EntityMap` holds at a position: the handle of the content there.
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub struct Occupant {
+    pub(crate) handle : Handle,
+    pub(crate) position : Position
+}
+
+impl Occupant {
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+}
+
+impl PositionAccessor for Occupant {
+    fn set_position(&mut self,new_position:Position) {
+        self.position = new_position;
+    }
+
+    fn get_position(&self) -> Position {
+        self.position
+    }
+}
+
+struct Slot<E> {
+    generation : u32,
+    entity : Option<E>
+}
+
+/// Entities indexed by `Handle`, reusing the slots of the removed ones.
+pub(crate) struct Store<E> {
+    slots : Vec<Slot<E>>,
+    free : Vec<u32>,
+    len : usize
+}
+
+impl<E> Store<E> {
+    pub(crate) fn new() -> Store<E> {
+        Store {
+            slots:Vec::new(),
+            free:Vec::new(),
+            len:0
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Handle the next call to `insert` will return
+    pub(crate) fn next_handle(&self) -> Handle {
+        match self.free.last() {
+            Some(&index) => Handle {index,generation:self.slots[index as usize].generation},
+            None => Handle {index:self.slots.len() as u32,generation:0},
+        }
+    }
+
+    pub(crate) fn insert(&mut self,entity:E) -> Handle {
+        let handle = self.next_handle();
+        if self.free.pop().is_none() {
+            self.slots.push(Slot {generation:0,entity:None});
+        }
+        self.slots[handle.index as usize].entity = Some(entity);
+        self.len += 1;
+        handle
+    }
+
+    pub(crate) fn get(&self,handle:Handle) -> Option<&E> {
+        self.slots.get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.entity.as_ref())
+    }
+
+    pub(crate) fn get_mut(&mut self,handle:Handle) -> Option<&mut E> {
+        self.slots.get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.entity.as_mut())
+    }
+
+    pub(crate) fn remove(&mut self,handle:Handle) -> Option<E> {
+        let entity = {
+            let slot = self.slots.get_mut(handle.index as usize)
+                .filter(|slot| slot.generation == handle.generation)?;
+            let entity = slot.entity.take()?;
+            slot.generation = slot.generation.wrapping_add(1);
+            entity
+        };
+        self.free.push(handle.index);
+        self.len -= 1;
+        Some(entity)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item=(Handle,&E)> {
+        self.slots.iter().enumerate().filter_map(|(index,slot)| {
+            slot.entity.as_ref().map(|entity| (Handle {index:index as u32,generation:slot.generation},entity))
+        })
+    }
+}
+
+/// A map which keeps its contents in a store indexed by `Handle`, so that the position of
+/// a content is found in constant time, without the content having to keep track of it.
+///
+/// The positions themselves are held by a `Map` of `Occupant`s, available through `grid`
+/// to run the `HexMap` algorithms; every operation follows the rules of `Map`.
+pub struct EntityMap<T,Bg : Default + AllowContent> {
+    grid : Map<Occupant,Bg>,
+    store : Store<(Position,T)>
+}
+
+impl<T,Bg> EntityMap<T,Bg> where Bg : Default + AllowContent {
+    /// Creates a map shaped as a parallelogram, see `MapShape::Parallelogram`.
+    pub fn new(length:(i32,i32),offset:Position) -> Result<EntityMap<T,Bg>> {
+        Self::with_shape(MapShape::Parallelogram {width:length.0,height:length.1},offset)
+    }
+
+    pub fn with_shape(shape:MapShape,offset:Position) -> Result<EntityMap<T,Bg>> {
+        Self::with_wrap(shape,offset,Wrap::None)
+    }
+
+    /// See `Map::with_wrap`
+    pub fn with_wrap(shape:MapShape,offset:Position,wrap:Wrap) -> Result<EntityMap<T,Bg>> {
+        Ok(EntityMap {
+            grid:Map::with_wrap(shape,offset,wrap)?,
+            store:Store::new()
+        })
+    }
+
+    /// Handles of the contents by position, along with the `Bg`s.
+    pub fn grid(&self) -> &Map<Occupant,Bg> {
+        &self.grid
+    }
+
+    pub fn shape(&self) -> MapShape {
+        self.grid.shape()
+    }
+
+    pub fn offset(&self) -> Position {
+        self.grid.offset()
+    }
+
+    /// Whether `position` is part of the map
+    pub fn contains(&self,position:Position) -> bool {
+        self.grid.contains(position)
+    }
+
+    /// Number of contents in the map
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.len() == 0
+    }
+
+    pub fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        self.grid.position_status(position)
+    }
+
+    /// Whether `handle` still refers to a content of the map
+    pub fn is_valid(&self,handle:Handle) -> bool {
+        self.store.get(handle).is_some()
+    }
+
+    /// Position of the content of `handle`, or `None` if it is no longer in the map
+    pub fn position_of(&self,handle:Handle) -> Option<Position> {
+        self.store.get(handle).map(|&(position,_)| position)
+    }
+
+    /// Content of `handle` and its position, or `None` if it is no longer in the map
+    pub fn get(&self,handle:Handle) -> Option<(Position,&T)> {
+        self.store.get(handle).map(|&(position,ref content)| (position,content))
+    }
+
+    pub fn get_mut(&mut self,handle:Handle) -> Option<(Position,&mut T)> {
+        self.store.get_mut(handle).map(|&mut (position,ref mut content)| (position,content))
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn handle_at(&self,position:Position) -> Result<Option<Handle>> {
+        Ok(self.grid.get_contents(position)?.map(|occupant| occupant.handle))
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_contents(&self,position:Position) -> Result<Option<&T>> {
+        Ok(self.handle_at(position)?.and_then(|handle| self.get(handle)).map(|(_,content)| content))
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_contents_mut(&mut self,position:Position) -> Result<Option<&mut T>> {
+        match self.handle_at(position)? {
+            Some(handle) => Ok(self.get_mut(handle).map(|(_,content)| content)),
+            None => Ok(None),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg(&self,position:Position) -> Result<&Bg> {
+        self.grid.get_bg(position)
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg_mut(&mut self,position:Position) -> Result<&mut Bg> {
+        self.grid.get_bg_mut(position)
+    }
+
+    /// Updates the store from the `Occupant` at `position`, which `Map` keeps up to date.
+    fn sync(&mut self,position:Position) {
+        if let Ok(Some(occupant)) = self.grid.get_contents(position) {
+            let occupant = *occupant;
+            if let Some(entity) = self.store.get_mut(occupant.handle) {
+                entity.0 = occupant.position;
+            }
+        }
+    }
+
+    /// Put a new content at `Position`, and returns its handle.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `AlreadyOccupied` if Position is busy
+    /// * `ForbiddenLocation` if the `Bg` of Position does not allow contents
+    pub fn create_content(&mut self,position:Position,new_content:T) -> Result<Handle> {
+        let handle = self.store.next_handle();
+        self.grid.create_content(position,Occupant {handle,position})?;
+        let position = self.grid.get_contents(position)?.expect("Just created").position;
+        Ok(self.store.insert((position,new_content)))
+    }
+
+    /// Extract the content of `handle` from the map.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingTarget` if the content of `handle` is no longer in the map
+    pub fn remove(&mut self,handle:Handle) -> Result<T> {
+        let position = self.position_of(handle).ok_or(Error::new(Reason::MissingTarget))?;
+        self.extract_content(position)
+    }
+
+    /// Extract a content at `Position`; its handle becomes invalid.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `MissingTarget` if Position has no content (`None`)
+    pub fn extract_content(&mut self,position:Position) -> Result<T> {
+        let occupant = self.grid.extract_content(position)?;
+        let (_,content) = self.store.remove(occupant.handle).expect("Occupant without content");
+        Ok(content)
+    }
+
+    /// Swap 2 elements; their handles follow them.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if one or more positions is not valid
+    /// * `MissingTarget` if one the 2 position has no content (`None`)
+    pub fn swap_contents(&mut self,pos_1:Position,pos_2:Position) -> Result<()> {
+        self.grid.swap_contents(pos_1,pos_2)?;
+        self.sync(pos_1);
+        self.sync(pos_2);
+        Ok(())
+    }
+
+    /// Move an element from a position to another; its handle follows it.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if one or more positions are not valid
+    /// * `MissingTarget` if the initial position has no element
+    /// * `AlreadyOccupied` if the final position is busy
+    /// * `ForbiddenLocation` if the `Bg` of the final position does not allow contents
+    pub fn move_contents(&mut self,from:Position,to:Position) -> Result<()> {
+        self.grid.move_contents(from,to)?;
+        self.sync(to);
+        Ok(())
+    }
+
+    /// Move the content of `handle` to `to`.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingTarget` if the content of `handle` is no longer in the map
+    /// * Same as `move_contents` otherwise
+    pub fn move_entity(&mut self,handle:Handle,to:Position) -> Result<()> {
+        let from = self.position_of(handle).ok_or(Error::new(Reason::MissingTarget))?;
+        self.move_contents(from,to)
+    }
+
+    /// Every content with its handle and its position, in no particular order
+    pub fn iter_entities(&self) -> impl Iterator<Item=(Handle,Position,&T)> {
+        self.store.iter().map(|(handle,&(position,ref content))| (handle,position,content))
+    }
+
+    pub fn iter_bg(&self) -> MapIter<Iter<'_,Bg>> {
+        self.grid.iter_bg()
+    }
+
+    pub fn iter_bg_mut(&mut self) -> MapIter<IterMut<'_,Bg>> {
+        self.grid.iter_bg_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::BusyPolicy;
+    use map::tests::Bg;
+
+    fn sample_map() -> EntityMap<&'static str,Bg> {
+        EntityMap::new((10,10),Position::new(-5,-5)).unwrap()
+    }
+
+    #[test]
+    fn routine_test(){
+        let mut map = sample_map();
+        let (a,b,c) = (Position::new(0,0),Position::new(2,0),Position::new(3,-1));
+        let first = map.create_content(a,"first").unwrap();
+        let second = map.create_content(b,"second").unwrap();
+        assert_ne!(first,second);
+        assert_eq!(map.len(),2);
+        assert_eq!(map.create_content(a,"third").unwrap_err(),Error::new(Reason::AlreadyOccupied));
+        assert_eq!(map.len(),2);
+        assert_eq!(map.get(first),Some((a,&"first")));
+        assert_eq!(map.handle_at(b).unwrap(),Some(second));
+
+        map.move_contents(a,c).unwrap();
+        assert_eq!(map.position_of(first),Some(c));
+        map.swap_contents(c,b).unwrap();
+        assert_eq!(map.position_of(first),Some(b));
+        assert_eq!(map.position_of(second),Some(c));
+        assert_eq!(map.get_contents(c).unwrap(),Some(&"second"));
+        map.move_entity(second,a).unwrap();
+        assert_eq!(map.handle_at(a).unwrap(),Some(second));
+        assert_eq!(map.handle_at(c).unwrap(),None);
+        *map.get_mut(second).unwrap().1 = "moved";
+        assert_eq!(map.get_contents(a).unwrap(),Some(&"moved"));
+
+        assert_eq!(map.extract_content(a).unwrap(),"moved");
+        assert!(!map.is_valid(second));
+        assert_eq!(map.position_of(second),None);
+        assert_eq!(map.move_entity(second,c).unwrap_err(),Error::new(Reason::MissingTarget));
+        let third = map.create_content(c,"third").unwrap();
+        assert_ne!(third,second);
+        assert!(!map.is_valid(second));
+        assert_eq!(map.remove(first).unwrap(),"first");
+        assert_eq!(map.iter_entities().collect::<Vec<_>>(),vec![(third,c,&"third")]);
+    }
+
+    #[test]
+    fn wrap_and_grid(){
+        let mut map : EntityMap<&str,Bg> = EntityMap::with_wrap(MapShape::Parallelogram {width:10,height:10},
+                                                                Position::new(-5,-5),Wrap::Horizontal).unwrap();
+        let unit = map.create_content(Position::new(5,0),"unit").unwrap();
+        assert_eq!(map.position_of(unit),Some(Position::new(-5,0)));
+        map.move_contents(Position::new(-5,0),Position::new(-6,0)).unwrap();
+        assert_eq!(map.position_of(unit),Some(Position::new(4,0)));
+        let (path,_) = map.grid().find_path(Position::new(0,0),Position::new(4,0),BusyPolicy::PassableAtGoal,|_| Some(1)).unwrap();
+        assert_eq!(path.len(),5);
+        assert_eq!(map.grid().get_contents(Position::new(4,0)).unwrap().unwrap().handle(),unit);
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashSet;
+use std::slice::{Iter,IterMut};
+use error::{Error,Reason,Result};
+use pos::{Position,Rotation};
+use map::{Map,MapShape,MapIter,Wrap,HexMap,AllowContent,PositionStatus};
+use map::entity::{Handle,Occupant,Store};
+
+/// Positions covered by a content, relative to its anchor.
+///
+/// The anchor `(0,0)` is always covered, and rotations turn around it.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Footprint {
+    tiles : Vec<Position>
+}
+
+impl Footprint {
+    /// Footprint covering the anchor and `tiles`, without duplicates.
+    pub fn new<I>(tiles:I) -> Footprint where I : IntoIterator<Item=Position> {
+        let mut footprint = Footprint::single();
+        for tile in tiles {
+            if !footprint.tiles.contains(&tile) {
+                footprint.tiles.push(tile);
+            }
+        }
+        footprint
+    }
+
+    /// Footprint covering only the anchor
+    pub fn single() -> Footprint {
+        Footprint {tiles:vec![Position::new(0,0)]}
+    }
+
+    /// Footprint covering every position within `radius` of the anchor
+    pub fn hexagon(radius:i32) -> Footprint {
+        Footprint::new(Position::new(0,0).iter_range(radius))
+    }
+
+    /// Covered positions relative to the anchor, the anchor first
+    pub fn tiles(&self) -> &[Position] {
+        &self.tiles
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Always `false`, since the anchor is covered
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Same footprint turned by a sixth of a turn around the anchor
+    pub fn rotate(&self,rotation:Rotation) -> Footprint {
+        Footprint {tiles:self.tiles.iter().map(|tile| tile.rotate(rotation)).collect()}
+    }
+
+    /// Positions covered when the anchor is at `anchor`
+    pub fn at(&self,anchor:Position) -> impl Iterator<Item=Position> + '_ {
+        self.tiles.iter().map(move |&tile| anchor + tile)
+    }
+}
+
+struct Large<T> {
+    anchor : Position,
+    footprint : Footprint,
+    content : T
+}
+
+/// A map of contents covering several positions, such as buildings or large units.
+///
+/// Every content has a `Footprint` placed at an anchor, and is placed, moved or rotated
+/// only if every position it would cover is part of the map, allows contents and is not
+/// covered by another content. Contents are identified by a `Handle`, as in `EntityMap`,
+/// and the grid of `Occupant`s holds the handle of the content covering each position.
+pub struct FootprintMap<T,Bg : Default + AllowContent> {
+    grid : Map<Occupant,Bg>,
+    store : Store<Large<T>>
+}
+
+impl<T,Bg> FootprintMap<T,Bg> where Bg : Default + AllowContent {
+    /// Creates a map shaped as a parallelogram, see `MapShape::Parallelogram`.
+    pub fn new(length:(i32,i32),offset:Position) -> Result<FootprintMap<T,Bg>> {
+        Self::with_shape(MapShape::Parallelogram {width:length.0,height:length.1},offset)
+    }
+
+    pub fn with_shape(shape:MapShape,offset:Position) -> Result<FootprintMap<T,Bg>> {
+        Self::with_wrap(shape,offset,Wrap::None)
+    }
+
+    /// See `Map::with_wrap`
+    pub fn with_wrap(shape:MapShape,offset:Position,wrap:Wrap) -> Result<FootprintMap<T,Bg>> {
+        Ok(FootprintMap {
+            grid:Map::with_wrap(shape,offset,wrap)?,
+            store:Store::new()
+        })
+    }
+
+    /// Handles of the contents by covered position, along with the `Bg`s.
+    pub fn grid(&self) -> &Map<Occupant,Bg> {
+        &self.grid
+    }
+
+    pub fn shape(&self) -> MapShape {
+        self.grid.shape()
+    }
+
+    pub fn offset(&self) -> Position {
+        self.grid.offset()
+    }
+
+    /// Whether `position` is part of the map
+    pub fn contains(&self,position:Position) -> bool {
+        self.grid.contains(position)
+    }
+
+    /// Number of contents in the map
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.len() == 0
+    }
+
+    pub fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        self.grid.position_status(position)
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg(&self,position:Position) -> Result<&Bg> {
+        self.grid.get_bg(position)
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg_mut(&mut self,position:Position) -> Result<&mut Bg> {
+        self.grid.get_bg_mut(position)
+    }
+
+    /// Handle of the content covering `position`
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn handle_at(&self,position:Position) -> Result<Option<Handle>> {
+        Ok(self.grid.get_contents(position)?.map(|occupant| occupant.handle()))
+    }
+
+    /// Whether `handle` still refers to a content of the map
+    pub fn is_valid(&self,handle:Handle) -> bool {
+        self.store.get(handle).is_some()
+    }
+
+    /// Anchor, footprint and content of `handle`, or `None` if it is no longer in the map
+    pub fn get(&self,handle:Handle) -> Option<(Position,&Footprint,&T)> {
+        self.store.get(handle).map(|large| (large.anchor,&large.footprint,&large.content))
+    }
+
+    pub fn get_mut(&mut self,handle:Handle) -> Option<&mut T> {
+        self.store.get_mut(handle).map(|large| &mut large.content)
+    }
+
+    /// Checks that `footprint` can be placed at `anchor`, ignoring the positions covered
+    /// by `ignored`, and returns the positions it would cover.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if a covered position is not valid
+    /// * `AlreadyOccupied` if a covered position is covered by another content, or if the
+    ///   footprint overlaps itself on a map wrapping around
+    /// * `ForbiddenLocation` if the `Bg` of a covered position does not allow contents
+    pub fn check_placement(&self,anchor:Position,footprint:&Footprint,ignored:Option<Handle>) -> Result<Vec<Position>> {
+        let mut covered = Vec::with_capacity(footprint.len());
+        let mut seen = HashSet::new();
+        for tile in footprint.at(anchor) {
+            let tile = self.grid.canonicalize(tile)?;
+            if !seen.insert(tile) {
+                return Err(Error::new(Reason::AlreadyOccupied));
+            }
+            let (occupant,bg) = self.grid.get(tile)?;
+            match *occupant {
+                Some(occupant) if Some(occupant.handle()) != ignored => {
+                    return Err(Error::new(Reason::AlreadyOccupied));
+                },
+                _ if !bg.is_content_allowed() => return Err(Error::new(Reason::ForbiddenLocation)),
+                _ => covered.push(tile),
+            }
+        }
+        Ok(covered)
+    }
+
+    fn cover(&mut self,handle:Handle,covered:&[Position]) {
+        for &tile in covered {
+            self.grid.create_content(tile,Occupant {handle,position:tile}).expect("Placement checked");
+        }
+    }
+
+    fn uncover(&mut self,handle:Handle) {
+        let tiles : Vec<Position> = {
+            let large = self.store.get(handle).expect("Valid handle");
+            large.footprint.at(large.anchor).collect()
+        };
+        for tile in tiles {
+            self.grid.extract_content(tile).expect("Covered position");
+        }
+    }
+
+    /// Put a new content with `footprint` at `anchor`, and returns its handle.
+    ///
+    /// # Errors
+    ///
+    /// See `check_placement`
+    pub fn create_content(&mut self,anchor:Position,footprint:Footprint,content:T) -> Result<Handle> {
+        let covered = self.check_placement(anchor,&footprint,None)?;
+        let handle = self.store.next_handle();
+        self.cover(handle,&covered);
+        Ok(self.store.insert(Large {anchor:covered[0],footprint,content}))
+    }
+
+    /// Places the content of `handle` at `anchor` with `footprint`, if it fits.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingTarget` if the content of `handle` is no longer in the map
+    /// * See `check_placement` otherwise
+    pub fn place_entity(&mut self,handle:Handle,anchor:Position,footprint:Footprint) -> Result<()> {
+        if !self.is_valid(handle) {
+            return Err(Error::new(Reason::MissingTarget));
+        }
+        let covered = self.check_placement(anchor,&footprint,Some(handle))?;
+        self.uncover(handle);
+        self.cover(handle,&covered);
+        let large = self.store.get_mut(handle).expect("Valid handle");
+        large.anchor = covered[0];
+        large.footprint = footprint;
+        Ok(())
+    }
+
+    /// Moves the content of `handle` so that its anchor is at `anchor`.
+    ///
+    /// # Errors
+    ///
+    /// See `place_entity`
+    pub fn move_entity(&mut self,handle:Handle,anchor:Position) -> Result<()> {
+        let footprint = self.get(handle).ok_or(Error::new(Reason::MissingTarget))?.1.clone();
+        self.place_entity(handle,anchor,footprint)
+    }
+
+    /// Turns the content of `handle` by a sixth of a turn around its anchor.
+    ///
+    /// # Errors
+    ///
+    /// See `place_entity`
+    pub fn rotate_entity(&mut self,handle:Handle,rotation:Rotation) -> Result<()> {
+        let (anchor,footprint) = {
+            let (anchor,footprint,_) = self.get(handle).ok_or(Error::new(Reason::MissingTarget))?;
+            (anchor,footprint.rotate(rotation))
+        };
+        self.place_entity(handle,anchor,footprint)
+    }
+
+    /// Extract the content of `handle` from the map.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingTarget` if the content of `handle` is no longer in the map
+    pub fn remove(&mut self,handle:Handle) -> Result<T> {
+        if !self.is_valid(handle) {
+            return Err(Error::new(Reason::MissingTarget));
+        }
+        self.uncover(handle);
+        Ok(self.store.remove(handle).expect("Valid handle").content)
+    }
+
+    /// Every content with its handle, its anchor and its footprint, in no particular order
+    pub fn iter_entities(&self) -> impl Iterator<Item=(Handle,Position,&Footprint,&T)> {
+        self.store.iter().map(|(handle,large)| (handle,large.anchor,&large.footprint,&large.content))
+    }
+
+    pub fn iter_bg(&self) -> MapIter<Iter<'_,Bg>> {
+        self.grid.iter_bg()
+    }
+
+    pub fn iter_bg_mut(&mut self) -> MapIter<IterMut<'_,Bg>> {
+        self.grid.iter_bg_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::tests::Bg;
+
+    fn sample_map() -> FootprintMap<&'static str,Bg> {
+        FootprintMap::with_shape(MapShape::Hexagon {radius:5},Position::new(0,0)).unwrap()
+    }
+
+    fn triangle() -> Footprint {
+        Footprint::new(vec![Position::new(1,0),Position::new(0,1)])
+    }
+
+    #[test]
+    fn footprint(){
+        assert_eq!(Footprint::single().len(),1);
+        assert_eq!(Footprint::hexagon(1).len(),7);
+        assert_eq!(Footprint::new(vec![Position::new(0,0),Position::new(1,0),Position::new(1,0)]).len(),2);
+        let rotated = triangle().rotate(Rotation::Clockwise);
+        assert_eq!(rotated.tiles(),&[Position::new(0,0),Position::new(1,-1),Position::new(1,0)]);
+        let mut footprint = triangle();
+        for _ in 0..6 {
+            footprint = footprint.rotate(Rotation::CounterClockwise);
+        }
+        assert_eq!(footprint,triangle());
+        assert_eq!(Footprint::hexagon(1).rotate(Rotation::Clockwise).at(Position::new(2,2)).count(),7);
+    }
+
+    #[test]
+    fn placement(){
+        let mut map = sample_map();
+        let boss = map.create_content(Position::new(0,0),Footprint::hexagon(1),"boss").unwrap();
+        for &position in &[Position::new(0,0),Position::new(1,-1),Position::new(-1,0)] {
+            assert_eq!(map.handle_at(position).unwrap(),Some(boss));
+        }
+        assert_eq!(map.create_content(Position::new(1,0),triangle(),"house").unwrap_err(),
+                   Error::new(Reason::AlreadyOccupied));
+        assert_eq!(map.create_content(Position::new(5,0),triangle(),"house").unwrap_err(),
+                   Error::new(Reason::OutOfRange));
+        map.get_bg_mut(Position::new(3,1)).unwrap().kind = String::from("Obstacle");
+        assert_eq!(map.create_content(Position::new(3,0),triangle(),"house").unwrap_err(),
+                   Error::new(Reason::ForbiddenLocation));
+        let house = map.create_content(Position::new(3,-1),triangle(),"house").unwrap();
+        assert_eq!(map.len(),2);
+        assert_eq!(map.grid().iter_contents().filter(|&(_,occupant)| occupant.is_some()).count(),10);
+
+        // moving onto its own previous positions is allowed, onto another content is not
+        map.move_entity(boss,Position::new(-1,0)).unwrap();
+        assert_eq!(map.handle_at(Position::new(1,-1)).unwrap(),None);
+        assert_eq!(map.handle_at(Position::new(-2,0)).unwrap(),Some(boss));
+        assert_eq!(map.move_entity(boss,Position::new(2,-1)).unwrap_err(),Error::new(Reason::AlreadyOccupied));
+        assert_eq!(map.get(boss).unwrap().0,Position::new(-1,0));
+
+        // turned once, the house covers (4,-2) instead of (3,0), and (3,-2) when turned twice
+        map.rotate_entity(house,Rotation::Clockwise).unwrap();
+        assert_eq!(map.handle_at(Position::new(4,-2)).unwrap(),Some(house));
+        assert_eq!(map.handle_at(Position::new(3,0)).unwrap(),None);
+        map.get_bg_mut(Position::new(3,-2)).unwrap().kind = String::from("Obstacle");
+        assert_eq!(map.rotate_entity(house,Rotation::Clockwise).unwrap_err(),Error::new(Reason::ForbiddenLocation));
+        let guard = map.create_content(Position::new(3,0),Footprint::single(),"guard").unwrap();
+        assert_eq!(map.rotate_entity(house,Rotation::CounterClockwise).unwrap_err(),Error::new(Reason::AlreadyOccupied));
+        assert_eq!(map.handle_at(Position::new(4,-2)).unwrap(),Some(house));
+        map.remove(guard).unwrap();
+        map.rotate_entity(house,Rotation::CounterClockwise).unwrap();
+        assert_eq!(map.get(house).unwrap().1,&triangle());
+
+        assert_eq!(map.remove(boss).unwrap(),"boss");
+        assert_eq!(map.handle_at(Position::new(-1,0)).unwrap(),None);
+        assert_eq!(map.move_entity(boss,Position::new(0,0)).unwrap_err(),Error::new(Reason::MissingTarget));
+        assert_eq!(map.iter_entities().count(),1);
+    }
+
+    #[test]
+    fn wrap(){
+        let mut map : FootprintMap<&str,Bg> = FootprintMap::with_wrap(MapShape::Parallelogram {width:2,height:4},
+                                                                      Position::new(0,0),Wrap::Horizontal).unwrap();
+        assert_eq!(map.create_content(Position::new(0,1),Footprint::hexagon(1),"too wide").unwrap_err(),
+                   Error::new(Reason::AlreadyOccupied));
+        let wide = map.create_content(Position::new(1,1),Footprint::new(vec![Position::new(1,0)]),"wide").unwrap();
+        assert_eq!(map.handle_at(Position::new(0,1)).unwrap(),Some(wide));
+        map.move_entity(wide,Position::new(3,2)).unwrap();
+        assert_eq!(map.get(wide).unwrap().0,Position::new(1,2));
+    }
+}
@@ -0,0 +1,254 @@
+use std::f64::consts::PI;
+use std::collections::HashSet;
+use error::Result;
+use pos::{Position,LINE_EPSILON};
+use map::{Map,HexMap,PositionAccessor,AllowContent};
+
+const ANGLE_EPSILON : f64 = 1e-9 ;
+
+/// Angle of the center of `position` seen from the center of `(0,0)`, in `[0,2π)`, with
+/// hexes laid out as in `Orientation::Pointy`.
+fn angle_of(position:Position) -> f64 {
+    let x = 3f64.sqrt() * (position.x as f64 + position.y as f64 / 2.0);
+    let y = 1.5 * position.y as f64;
+    let angle = y.atan2(x);
+    if angle < 0.0 { angle + 2.0 * PI } else { angle }
+}
+
+/// Angles covered by the hex `position` seen from the center of `(0,0)`, which must not
+/// be `position` itself. The end may go beyond `2π`.
+fn extent_of(position:Position) -> (f64,f64) {
+    let center = angle_of(position);
+    let (cx, cy) = (3f64.sqrt() * (position.x as f64 + position.y as f64 / 2.0), 1.5 * position.y as f64);
+    let (mut min_delta, mut max_delta) = (0f64,0f64);
+    for i in 0..6 {
+        let corner_angle = (30.0 + 60.0 * i as f64).to_radians();
+        let (x, y) = (cx + corner_angle.cos(), cy + corner_angle.sin());
+        let mut delta = y.atan2(x) - center;
+        if delta > PI {
+            delta -= 2.0 * PI;
+        } else if delta < -PI {
+            delta += 2.0 * PI;
+        }
+        min_delta = min_delta.min(delta);
+        max_delta = max_delta.max(delta);
+    }
+    let start = center + min_delta;
+    let start = if start < 0.0 { start + 2.0 * PI } else { start };
+    (start, start + max_delta - min_delta)
+}
+
+/// Union of the angles hidden by opaque hexes, kept as sorted and disjoint intervals.
+/// Intervals touching each other are merged, so that nothing can be seen through the
+/// corner where two opaque hexes meet.
+#[derive(Default)]
+struct Shadows {
+    intervals:Vec<(f64,f64)>
+}
+
+impl Shadows {
+    fn add(&mut self,interval:(f64,f64)) {
+        self.intervals.push(interval);
+        self.intervals.sort_by(|a,b| a.0.partial_cmp(&b.0).unwrap());
+        let mut merged : Vec<(f64,f64)> = Vec::with_capacity(self.intervals.len());
+        for &(start,end) in &self.intervals {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 + ANGLE_EPSILON => last.1 = last.1.max(end),
+                _ => merged.push((start,end)),
+            }
+        }
+        // the last interval may wrap around and reach the first ones
+        while merged.len() > 1 && merged[merged.len() - 1].1 - 2.0 * PI >= merged[0].0 - ANGLE_EPSILON {
+            let first = merged.remove(0);
+            let last = merged.last_mut().unwrap();
+            last.1 = last.1.max(first.1 + 2.0 * PI);
+        }
+        self.intervals = merged;
+    }
+
+    /// Whether `angle` is strictly inside the shadows
+    fn hides_angle(&self,angle:f64) -> bool {
+        self.intervals.iter().any(|&(start,end)| {
+            [angle,angle + 2.0 * PI].iter().any(|&a| start + ANGLE_EPSILON < a && a < end - ANGLE_EPSILON)
+        })
+    }
+
+    /// Whether every angle of `interval` is in the shadows
+    fn hides_interval(&self,interval:(f64,f64)) -> bool {
+        self.intervals.iter().any(|&(start,end)| {
+            [0.0,2.0 * PI].iter().any(|&shift| {
+                start - ANGLE_EPSILON <= interval.0 + shift && interval.1 + shift <= end + ANGLE_EPSILON
+            })
+        })
+    }
+}
+
+/// See `HexMap::field_of_view`
+pub(crate) fn field_of_view<M,F>(map:&M,origin:Position,radius:i32,mut opaque:F) -> Result<Vec<Position>>
+where M : HexMap, F : FnMut(&M::Bg) -> bool {
+    let origin = map.canonicalize(origin)?;
+    let mut visible = vec![origin];
+    // on a map wrapping around, a position may be seen from several directions
+    let mut seen : HashSet<Position> = visible.iter().cloned().collect();
+    let mut shadows = Shadows::default();
+    for r in 1..radius.abs() + 1 {
+        let mut new_shadows = Vec::new();
+        for position in origin.iter_ring(r) {
+            let (canonical,bg) = match (map.canonicalize(position),map.get(position)) {
+                (Ok(canonical),Ok((_,bg))) => (canonical,bg),
+                _ => continue,
+            };
+            let relative = position - origin;
+            let is_visible = if opaque(bg) {
+                let extent = extent_of(relative);
+                new_shadows.push(extent);
+                !shadows.hides_angle(angle_of(relative)) || !shadows.hides_interval(extent)
+            } else {
+                !shadows.hides_angle(angle_of(relative))
+            };
+            if is_visible && seen.insert(canonical) {
+                visible.push(canonical);
+            }
+        }
+        for extent in new_shadows {
+            shadows.add(extent);
+        }
+    }
+    Ok(visible)
+}
+
+/// See `HexMap::has_line_of_sight`
+pub(crate) fn has_line_of_sight<M,F>(map:&M,from:Position,to:Position,mut opaque:F) -> Result<bool>
+where M : HexMap, F : FnMut(&M::Bg) -> bool {
+    let from = map.canonicalize(from)?;
+    let to = map.nearest_image(from,map.canonicalize(to)?);
+    let distance = from.distance_to(to);
+    let mut candidates : HashSet<Position> = HashSet::new();
+    for &epsilon in &[LINE_EPSILON,-LINE_EPSILON] {
+        for position in from.line_to_with_nudge(to,epsilon) {
+            candidates.insert(position);
+            candidates.extend(position.neighbors().iter());
+        }
+    }
+    let mut shadows = Shadows::default();
+    for position in candidates {
+        let d = from.distance_to(position);
+        if d == 0 || d >= distance {
+            continue;
+        }
+        if let Ok((_,bg)) = map.get(position) {
+            if opaque(bg) {
+                shadows.add(extent_of(position - from));
+            }
+        }
+    }
+    Ok(distance == 0 || !shadows.hides_angle(angle_of(to - from)))
+}
+
+impl<T,Bg> Map<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    /// See `HexMap::field_of_view`
+    pub fn field_of_view<F>(&self,origin:Position,radius:i32,opaque:F) -> Result<Vec<Position>>
+    where F : FnMut(&Bg) -> bool {
+        field_of_view(self,origin,radius,opaque)
+    }
+
+    /// See `HexMap::has_line_of_sight`
+    pub fn has_line_of_sight<F>(&self,from:Position,to:Position,opaque:F) -> Result<bool>
+    where F : FnMut(&Bg) -> bool {
+        has_line_of_sight(self,from,to,opaque)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use map::tests::{Bg,sample_map};
+    use pos::Position;
+
+    fn opaque(bg:&Bg) -> bool {
+        bg.kind == "Wall"
+    }
+
+    fn set_walls(map:&mut ::map::Map<::map::tests::Dummy,Bg>,walls:&[(i32,i32)]) {
+        for &(x,y) in walls {
+            map.get_bg_mut(Position::new(x,y)).unwrap().kind = String::from("Wall");
+        }
+    }
+
+    #[test]
+    fn open_field(){
+        let map = sample_map();
+        let visible = map.field_of_view(Position::new(0,0),3,opaque).unwrap();
+        assert_eq!(visible,Position::new(0,0).spiral(3));
+    }
+
+    #[test]
+    fn map_borders(){
+        let map = sample_map();
+        let visible = map.field_of_view(Position::new(-5,-5),2,opaque).unwrap();
+        assert_eq!(visible.len(),1 + 2 + 3);
+        assert!(map.field_of_view(Position::new(5,5),2,opaque).is_err());
+    }
+
+    #[test]
+    fn wall_hides(){
+        let mut map = sample_map();
+        set_walls(&mut map,&[(1,0)]);
+        let visible = map.field_of_view(Position::new(0,0),4,opaque).unwrap();
+        assert!(visible.contains(&Position::new(1,0)));
+        assert!(!visible.contains(&Position::new(2,0)));
+        assert!(!visible.contains(&Position::new(3,0)));
+        assert!(visible.contains(&Position::new(2,-1)));
+        assert!(visible.contains(&Position::new(1,1)));
+        assert!(!map.has_line_of_sight(Position::new(0,0),Position::new(3,0),opaque).unwrap());
+        assert!(map.has_line_of_sight(Position::new(0,0),Position::new(2,-1),opaque).unwrap());
+    }
+
+    #[test]
+    fn corners(){
+        let mut map = sample_map();
+        // (0,0) -> (1,1) goes between (1,0) and (0,1)
+        set_walls(&mut map,&[(1,0)]);
+        assert!(map.has_line_of_sight(Position::new(0,0),Position::new(1,1),opaque).unwrap());
+        set_walls(&mut map,&[(0,1)]);
+        assert!(!map.has_line_of_sight(Position::new(0,0),Position::new(1,1),opaque).unwrap());
+        let visible = map.field_of_view(Position::new(0,0),2,opaque).unwrap();
+        assert!(!visible.contains(&Position::new(1,1)));
+        assert!(visible.contains(&Position::new(1,0)));
+        assert!(visible.contains(&Position::new(0,1)));
+    }
+
+    /// pseudo-random walls, so that tests stay deterministic
+    fn scattered_walls(seed:u32) -> ::map::Map<::map::tests::Dummy,Bg> {
+        let mut map = sample_map();
+        let mut state = seed;
+        for (_,bg) in map.iter_bg_mut() {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            if (state >> 16).is_multiple_of(5) {
+                bg.kind = String::from("Wall");
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn symmetry(){
+        for seed in 0..8 {
+            let map = scattered_walls(seed);
+            let positions : Vec<Position> = map.iter_bg()
+                .filter(|&(_,bg)| !opaque(bg))
+                .map(|(position,_)| position)
+                .collect();
+            for &a in &positions {
+                let fov = map.field_of_view(a,4,opaque).unwrap();
+                for &b in &positions {
+                    if a.distance_to(b) > 4 {
+                        continue;
+                    }
+                    let los = map.has_line_of_sight(a,b,opaque).unwrap();
+                    assert_eq!(los,map.has_line_of_sight(b,a,opaque).unwrap(),"{:?} {:?}",a,b);
+                    assert_eq!(los,fov.contains(&b),"{:?} {:?}",a,b);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use error::{Error,Reason,Result};
+use std::collections::{HashMap,HashSet};
+use pos::Position;
+use map::{PositionAccessor,AllowContent,PositionStatus,BusyPolicy,Reachable};
+use map::{path,fov};
+
+/// Iterator returned by `HexMap::iter`
+pub type HexMapIter<'a,T,Bg> = Box<dyn Iterator<Item=(Position,Option<&'a T>,&'a Bg)> + 'a>;
+
+/// Storage-independent access to a hexagonal map, so that algorithms (pathfinding, field
+/// of view, rendering...) are written once for `Map`, `SparseMap` and `ChunkedMap`.
+pub trait HexMap {
+    type Content : PositionAccessor ;
+    type Bg : AllowContent ;
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    fn get(&self,position:Position) -> Result<(Option<&Self::Content>,&Self::Bg)>;
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    fn get_mut(&mut self,position:Position) -> Result<(Option<&mut Self::Content>,&mut Self::Bg)>;
+
+    /// Whether `position` is part of the map
+    fn contains(&self,position:Position) -> bool;
+
+    /// Every position held by the map, with its content and its `Bg`.
+    ///
+    /// Backends which do not store every position only return the ones they hold: see
+    /// `SparseMap::iter_contents` and `ChunkedMap::iter_chunks`.
+    fn iter<'a>(&'a self) -> HexMapIter<'a,Self::Content,Self::Bg>;
+
+    fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        let (content,bg) = self.get(position)?;
+        let result = match content {
+            Some(_) => PositionStatus::Busy,
+            None if bg.is_content_allowed() => PositionStatus::Empty,
+            None => PositionStatus::Forbidden,
+        };
+        Ok(result)
+    }
+
+    /// The position of the map `position` stands for: itself, unless the map wraps around
+    /// (see `Map::with_wrap`).
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    fn canonicalize(&self,position:Position) -> Result<Position> {
+        if self.contains(position) {
+            Ok(position)
+        } else {
+            Err(Error::new(Reason::OutOfRange))
+        }
+    }
+
+    /// The position standing for `to` which is the closest to `from`: `to` itself, unless
+    /// the map wraps around.
+    fn nearest_image(&self,_from:Position,to:Position) -> Position {
+        to
+    }
+
+    /// Number of steps between `a` and `b`, going across the edges if the map wraps around.
+    fn distance(&self,a:Position,b:Position) -> i32 {
+        a.distance_to(self.nearest_image(a,b))
+    }
+
+    /// Positions of the map adjacent to `position`, canonicalized, in the same order as
+    /// `Position::neighbors`.
+    fn neighbors(&self,position:Position) -> Vec<Position> {
+        let position = self.canonicalize(position).unwrap_or(position);
+        let mut neighbors = Vec::with_capacity(6);
+        for &neighbor in position.neighbors().iter() {
+            if let Ok(neighbor) = self.canonicalize(neighbor) {
+                // a map one or two hexes wide wraps onto itself
+                if neighbor != position && !neighbors.contains(&neighbor) {
+                    neighbors.push(neighbor);
+                }
+            }
+        }
+        neighbors
+    }
+
+    /// Positions of the map within `radius` of `center`, canonicalized and without
+    /// duplicates, in the same order as `Position::in_range`.
+    fn in_range(&self,center:Position,radius:i32) -> Vec<Position> {
+        let mut seen = HashSet::new();
+        center.iter_range(radius)
+            .filter_map(|position| self.canonicalize(position).ok())
+            .filter(|&position| seen.insert(position))
+            .collect()
+    }
+
+    /// Most positions a search (`find_path`, `reachable`) expands before giving up, or
+    /// `None` when the map is finite and every search ends by itself.
+    ///
+    /// Maps whose `contains` is always true must give a limit, otherwise looking for a
+    /// path to a goal which cannot be reached would never end.
+    fn search_limit(&self) -> Option<usize> {
+        None
+    }
+
+    /// Shortest path from `from` to `to`, using A* with `distance` as heuristic.
+    ///
+    /// `cost_fn` gives the cost of entering a position from its `Bg`, or `None` if it
+    /// cannot be entered at all. `Forbidden` positions are never entered, `Busy` ones are
+    /// handled according to `busy`; the status of `from` itself is not checked.
+    ///
+    /// The result holds every position of the path, `from` and `to` included, and its total
+    /// cost, which is the cheapest one. A cost of 0 counts as 1, so that `distance` never
+    /// overestimates, and a path costing more than `u32::MAX` is not found. Among paths of
+    /// equal cost, the same one is always returned for a given map.
+    ///
+    /// Returns `None` if `from` or `to` are out of range, if there is no path, or if no
+    /// path was found within `search_limit` expanded positions.
+    fn find_path<F>(&self,from:Position,to:Position,busy:BusyPolicy,cost_fn:F) -> Option<(Vec<Position>,u32)>
+    where F : FnMut(&Self::Bg) -> Option<u32>, Self : Sized {
+        path::find_path(self,HashMap::new(),from,to,busy,cost_fn)
+    }
+
+    /// Every position which can be reached from `from` with a total cost of at most
+    /// `budget`, using Dijkstra's algorithm.
+    ///
+    /// `cost_fn` and `busy` work as in `find_path`, a cost of 0 counting as 1; a `Busy`
+    /// position reachable with `BusyPolicy::PassableAtGoal` is part of the result, but
+    /// nothing is reached through it. The search stops after `search_limit` positions, the
+    /// cheapest ones.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if `from` is not valid
+    fn reachable<F>(&self,from:Position,budget:u32,busy:BusyPolicy,cost_fn:F) -> Result<Reachable>
+    where F : FnMut(&Self::Bg) -> Option<u32>, Self : Sized {
+        path::reachable(self,HashMap::new(),from,budget,busy,cost_fn)
+    }
+
+    /// Every position of the map within `radius` of `origin` which can be seen from
+    /// `origin`, by increasing distance (same order as `Position::spiral`).
+    ///
+    /// This is a shadowcasting: hexes are visited ring by ring, and every opaque hex (as
+    /// told by `opaque` from its `Bg`) hides the angles it covers for the next rings.
+    /// A transparent hex is visible when the segment between the center of `origin` and
+    /// its own center is not hidden, so that `a` sees `b` if and only if `b` sees `a`; the
+    /// segment is hidden as well when it goes exactly between two opaque hexes. An opaque
+    /// hex is visible when any part of it is not hidden.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if `origin` is not valid
+    fn field_of_view<F>(&self,origin:Position,radius:i32,opaque:F) -> Result<Vec<Position>>
+    where F : FnMut(&Self::Bg) -> bool, Self : Sized {
+        fov::field_of_view(self,origin,radius,opaque)
+    }
+
+    /// Whether the segment between the centers of `from` and `to` is clear, using the
+    /// same rules as `field_of_view`: the result is the same either way, and `to` is
+    /// visible from `from` in the field of view when `to` is transparent.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if one or more positions are not valid
+    fn has_line_of_sight<F>(&self,from:Position,to:Position,opaque:F) -> Result<bool>
+    where F : FnMut(&Self::Bg) -> bool, Self : Sized {
+        fov::has_line_of_sight(self,from,to,opaque)
+    }
+}
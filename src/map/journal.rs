@@ -0,0 +1,413 @@
+use std::collections::VecDeque;
+use std::mem;
+use error::{Error,Reason,Result};
+use pos::Position;
+use map::{Map,PositionAccessor,AllowContent};
+
+/// A change of a `Map`, which once applied gives the change undoing it
+enum Edit<T,Bg> {
+    Insert(Position,T),
+    Remove(Position),
+    Replace(Position,T),
+    Move(Position,Position),
+    Swap(Position,Position),
+    SetBg(Position,Bg)
+}
+
+impl<T,Bg> Edit<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    fn apply(self,map:&mut Map<T,Bg>) -> Result<Edit<T,Bg>> {
+        match self {
+            Edit::Insert(position,content) => {
+                map.create_content(position,content)?;
+                Ok(Edit::Remove(position))
+            },
+            Edit::Remove(position) => Ok(Edit::Insert(position,map.extract_content(position)?)),
+            Edit::Replace(position,content) => Ok(Edit::Replace(position,map.replace_content(position,content)?)),
+            Edit::Move(from,to) => {
+                map.move_contents(from,to)?;
+                Ok(Edit::Move(to,from))
+            },
+            Edit::Swap(pos_1,pos_2) => {
+                map.swap_contents(pos_1,pos_2)?;
+                Ok(Edit::Swap(pos_1,pos_2))
+            },
+            Edit::SetBg(position,bg) => Ok(Edit::SetBg(position,mem::replace(map.get_bg_mut(position)?,bg))),
+        }
+    }
+
+    /// Applies an edit undoing or redoing a recorded step. The state it leads back to was
+    /// valid when it was recorded, so contents are written without checking the `Bg`s,
+    /// which may have changed since.
+    fn restore(self,map:&mut Map<T,Bg>) -> Edit<T,Bg> {
+        let index_of = |map:&Map<T,Bg>,position| map.pos_to_index(position).expect("Journal out of sync");
+        match self {
+            Edit::Insert(position,mut content) => {
+                let index = index_of(map,position);
+                content.set_position(map.index_to_pos(index).expect("Journal out of sync"));
+                assert!(map.contents_slice[index].is_none(),"Journal out of sync");
+                map.contents_slice[index] = Some(content);
+                Edit::Remove(position)
+            },
+            Edit::Move(from,to) => {
+                let (index_from,index_to) = (index_of(map,from),index_of(map,to));
+                let mut content = map.contents_slice[index_from].take().expect("Journal out of sync");
+                content.set_position(map.index_to_pos(index_to).expect("Journal out of sync"));
+                assert!(map.contents_slice[index_to].is_none(),"Journal out of sync");
+                map.contents_slice[index_to] = Some(content);
+                Edit::Move(to,from)
+            },
+            edit => edit.apply(map).expect("Journal out of sync"),
+        }
+    }
+}
+
+/// A `Map` recording its changes, so that they can be undone and redone.
+///
+/// Every change made through a `JournaledMap` is a step of its history. Only the last
+/// `limit` steps are kept, if a limit is given, and making a change after undoing some
+/// steps forgets the undone ones. Checkpoints name a state of the history to come back to.
+///
+/// ```
+/// use hexgrid::{Map,JournaledMap,AllowContent,PositionAccessor};
+/// use hexgrid::pos::Position;
+/// # #[derive(Clone,Default)]
+/// # struct Ground;
+/// # impl AllowContent for Ground {
+/// #     fn is_content_allowed(&self) -> bool { true }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Unit { position:Position }
+/// # impl PositionAccessor for Unit {
+/// #     fn set_position(&mut self,new_position:Position) { self.position = new_position; }
+/// #     fn get_position(&self) -> Position { self.position }
+/// # }
+/// let (a,b) = (Position::new(0,0),Position::new(1,0));
+/// let mut map : JournaledMap<Unit,Ground> = JournaledMap::new(Map::new((2,1),a).unwrap());
+/// map.create_content(a,Unit {position:a}).unwrap();
+/// map.checkpoint("turn start");
+/// map.move_contents(a,b).unwrap();
+/// map.restore_checkpoint("turn start").unwrap();
+/// assert!(map.map().get_contents(a).unwrap().is_some());
+/// assert!(map.redo());
+/// assert!(map.map().get_contents(b).unwrap().is_some());
+/// ```
+pub struct JournaledMap<T : PositionAccessor,Bg : Default + AllowContent> {
+    map : Map<T,Bg>,
+    undo : VecDeque<Edit<T,Bg>>,
+    redo : Vec<Edit<T,Bg>>,
+    /// Number of steps between the creation of the journal and the current state
+    state : u64,
+    checkpoints : Vec<(String,u64)>,
+    limit : Option<usize>
+}
+
+impl<T,Bg> JournaledMap<T,Bg> where T : PositionAccessor + Clone, Bg : Default + AllowContent + Clone {
+    /// Records the changes of `map`, without limit.
+    pub fn new(map:Map<T,Bg>) -> JournaledMap<T,Bg> {
+        JournaledMap {
+            map,
+            undo:VecDeque::new(),
+            redo:Vec::new(),
+            state:0,
+            checkpoints:Vec::new(),
+            limit:None
+        }
+    }
+
+    /// Records the changes of `map`, keeping only the last `limit` steps.
+    pub fn with_limit(map:Map<T,Bg>,limit:usize) -> JournaledMap<T,Bg> {
+        JournaledMap {
+            limit:Some(limit),
+            ..Self::new(map)
+        }
+    }
+
+    pub fn map(&self) -> &Map<T,Bg> {
+        &self.map
+    }
+
+    /// Stops recording, and gives back the map in its current state
+    pub fn into_map(self) -> Map<T,Bg> {
+        self.map
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    fn record(&mut self,edit:Edit<T,Bg>) -> Result<()> {
+        let undo = edit.apply(&mut self.map)?;
+        self.redo.clear();
+        let state = self.state;
+        self.checkpoints.retain(|&(_,checkpoint)| checkpoint <= state);
+        self.undo.push_back(undo);
+        self.state += 1;
+        if self.limit.is_some_and(|limit| self.undo.len() > limit) {
+            self.undo.pop_front();
+            let oldest = self.state - self.undo.len() as u64;
+            self.checkpoints.retain(|&(_,checkpoint)| checkpoint >= oldest);
+        }
+        Ok(())
+    }
+
+    /// See `Map::create_content`
+    pub fn create_content(&mut self,position:Position,new_content:T) -> Result<()> {
+        self.record(Edit::Insert(position,new_content))
+    }
+
+    /// See `Map::extract_content`
+    pub fn extract_content(&mut self,position:Position) -> Result<T> {
+        let content = self.map.get_contents(position)?.clone().ok_or(Error::new(Reason::MissingTarget))?;
+        self.record(Edit::Remove(position))?;
+        Ok(content)
+    }
+
+    /// See `Map::replace_content`
+    pub fn replace_content(&mut self,position:Position,new_content:T) -> Result<T> {
+        let content = self.map.get_contents(position)?.clone().ok_or(Error::new(Reason::MissingTarget))?;
+        self.record(Edit::Replace(position,new_content))?;
+        Ok(content)
+    }
+
+    /// See `Map::move_contents`
+    pub fn move_contents(&mut self,from:Position,to:Position) -> Result<()> {
+        self.record(Edit::Move(from,to))
+    }
+
+    /// See `Map::swap_contents`
+    pub fn swap_contents(&mut self,pos_1:Position,pos_2:Position) -> Result<()> {
+        self.record(Edit::Swap(pos_1,pos_2))
+    }
+
+    /// Sets the `Bg` of `position` and returns the previous one.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn set_bg(&mut self,position:Position,bg:Bg) -> Result<Bg> {
+        let previous = self.map.get_bg(position)?.clone();
+        self.record(Edit::SetBg(position,bg))?;
+        Ok(previous)
+    }
+
+    /// Changes the `Bg` of `position` with `modify`, as a single step.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn modify_bg<F>(&mut self,position:Position,modify:F) -> Result<()> where F : FnOnce(&mut Bg) {
+        let mut bg = self.map.get_bg(position)?.clone();
+        modify(&mut bg);
+        self.record(Edit::SetBg(position,bg))
+    }
+
+    /// Number of steps which can be undone
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Number of steps which can be redone
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Undoes the last step, and returns whether there was one.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop_back() {
+            Some(edit) => {
+                self.redo.push(edit.restore(&mut self.map));
+                self.state -= 1;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Redoes the last undone step, and returns whether there was one.
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(edit) => {
+                self.undo.push_back(edit.restore(&mut self.map));
+                self.state += 1;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Forgets every step and every checkpoint, keeping the map as it is.
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.checkpoints.clear();
+    }
+
+    /// Names the current state, replacing any checkpoint with the same name.
+    pub fn checkpoint(&mut self,name:&str) {
+        self.remove_checkpoint(name);
+        self.checkpoints.push((String::from(name),self.state));
+    }
+
+    /// Forgets a checkpoint, and returns whether it existed.
+    pub fn remove_checkpoint(&mut self,name:&str) -> bool {
+        let len = self.checkpoints.len();
+        self.checkpoints.retain(|(checkpoint,_)| checkpoint != name);
+        self.checkpoints.len() != len
+    }
+
+    /// Names of the checkpoints which can be restored, from the oldest
+    pub fn checkpoints(&self) -> Vec<&str> {
+        let mut checkpoints : Vec<&(String,u64)> = self.checkpoints.iter().collect();
+        checkpoints.sort_by_key(|&&(_,state)| state);
+        checkpoints.into_iter().map(|(name,_)| name.as_str()).collect()
+    }
+
+    /// Undoes or redoes steps until the state named `name` is reached.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingCheckpoint` if no checkpoint is named `name`: it may never have existed,
+    ///   or its state may have been forgotten
+    pub fn restore_checkpoint(&mut self,name:&str) -> Result<()> {
+        let target = self.checkpoints.iter()
+            .find(|(checkpoint,_)| checkpoint == name)
+            .map(|&(_,state)| state)
+            .ok_or(Error::new(Reason::MissingCheckpoint))?;
+        while self.state > target && self.undo() {}
+        while self.state < target && self.redo() {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::tests::{Dummy,Bg,sample_map};
+
+    impl Clone for Dummy {
+        fn clone(&self) -> Dummy {
+            Dummy {pos:self.pos,name:self.name.clone()}
+        }
+    }
+
+    fn dummy(name:&str) -> Dummy {
+        Dummy {pos:Position::default(),name:String::from(name)}
+    }
+
+    fn names(map:&JournaledMap<Dummy,Bg>) -> Vec<(Position,String)> {
+        map.map().iter_contents().filter_map(|(position,dummy)| {
+            dummy.as_ref().map(|dummy| {
+                assert_eq!(dummy.get_position(),position);
+                (position,dummy.name.clone())
+            })
+        }).collect()
+    }
+
+    #[test]
+    fn undo_redo(){
+        let mut map = JournaledMap::new(sample_map());
+        let (a,b,c) = (Position::new(0,0),Position::new(1,0),Position::new(2,0));
+        let mut states = vec![names(&map)];
+        map.create_content(a,dummy("a")).unwrap();
+        states.push(names(&map));
+        map.create_content(b,dummy("b")).unwrap();
+        states.push(names(&map));
+        map.swap_contents(a,b).unwrap();
+        states.push(names(&map));
+        map.move_contents(a,c).unwrap();
+        states.push(names(&map));
+        assert_eq!(map.replace_content(c,dummy("c")).unwrap().name,"b");
+        states.push(names(&map));
+        assert_eq!(map.extract_content(b).unwrap().name,"a");
+        states.push(names(&map));
+        map.modify_bg(b,|bg| bg.kind = String::from("Obstacle")).unwrap();
+        assert_eq!(map.set_bg(c,Bg {kind:String::from("Swamp")}).unwrap().kind,"");
+        assert_eq!(map.create_content(b,dummy("d")).unwrap_err(),Error::new(Reason::ForbiddenLocation));
+        assert_eq!(map.move_contents(a,Position::new(3,0)).unwrap_err(),Error::new(Reason::MissingTarget));
+        assert_eq!(map.undo_len(),8);
+
+        assert!(map.undo());
+        assert!(map.undo());
+        assert_eq!(map.map().get_bg(b).unwrap().kind,"");
+        for state in states.iter().rev().skip(1) {
+            assert!(map.undo());
+            assert_eq!(&names(&map),state);
+        }
+        assert!(!map.undo());
+        for state in states.iter().skip(1) {
+            assert!(map.redo());
+            assert_eq!(&names(&map),state);
+        }
+        assert_eq!(map.redo_len(),2);
+        map.create_content(a,dummy("new")).unwrap();
+        assert!(!map.redo());
+    }
+
+    #[test]
+    fn undo_over_forbidden_bg(){
+        let mut map = JournaledMap::new(sample_map());
+        let (a,b) = (Position::new(0,0),Position::new(1,0));
+        let obstacle = Bg {kind:String::from("Obstacle")};
+        map.create_content(a,dummy("a")).unwrap();
+        map.set_bg(a,obstacle.clone()).unwrap();
+        map.move_contents(a,b).unwrap();
+        assert!(map.undo());
+        assert_eq!(names(&map),vec![(a,String::from("a"))]);
+        assert!(map.redo());
+        assert_eq!(names(&map),vec![(b,String::from("a"))]);
+
+        map.set_bg(b,obstacle).unwrap();
+        map.extract_content(b).unwrap();
+        assert!(map.undo());
+        assert_eq!(names(&map),vec![(b,String::from("a"))]);
+        assert!(map.redo());
+        assert!(names(&map).is_empty());
+        while map.undo() {}
+        assert!(names(&map).is_empty());
+        while map.redo() {}
+        assert!(names(&map).is_empty());
+    }
+
+    #[test]
+    fn checkpoints(){
+        let mut map = JournaledMap::new(sample_map());
+        let (a,b) = (Position::new(0,0),Position::new(1,0));
+        map.create_content(a,dummy("a")).unwrap();
+        map.checkpoint("start");
+        map.move_contents(a,b).unwrap();
+        map.checkpoint("moved");
+        map.extract_content(b).unwrap();
+        assert_eq!(map.checkpoints(),vec!["start","moved"]);
+
+        map.restore_checkpoint("start").unwrap();
+        assert_eq!(names(&map),vec![(a,String::from("a"))]);
+        map.restore_checkpoint("moved").unwrap();
+        assert_eq!(names(&map),vec![(b,String::from("a"))]);
+        assert_eq!(map.restore_checkpoint("end").unwrap_err(),Error::new(Reason::MissingCheckpoint));
+
+        // a new step after "start" forgets "moved"
+        map.restore_checkpoint("start").unwrap();
+        map.swap_contents(a,a).unwrap();
+        assert_eq!(map.checkpoints(),vec!["start"]);
+        assert!(map.remove_checkpoint("start"));
+        assert!(!map.remove_checkpoint("start"));
+    }
+
+    #[test]
+    fn limit(){
+        let mut map = JournaledMap::with_limit(sample_map(),3);
+        let positions : Vec<Position> = (0..5).map(|x| Position::new(x - 2,0)).collect();
+        map.create_content(positions[0],dummy("a")).unwrap();
+        map.checkpoint("created");
+        for pair in positions.windows(2) {
+            map.move_contents(pair[0],pair[1]).unwrap();
+        }
+        assert_eq!(map.undo_len(),3);
+        assert_eq!(map.restore_checkpoint("created").unwrap_err(),Error::new(Reason::MissingCheckpoint));
+        while map.undo() {}
+        assert_eq!(names(&map),vec![(positions[1],String::from("a"))]);
+        map.clear_history();
+        assert_eq!((map.undo_len(),map.redo_len()),(0,0));
+        assert_eq!(map.into_map().iter_contents().filter(|&(_,dummy)| dummy.is_some()).count(),1);
+    }
+}
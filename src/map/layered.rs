@@ -0,0 +1,480 @@
+use std::slice::{Iter,IterMut};
+use error::{Error,Reason,Result};
+use pos::Position;
+use map::{MapShape,MapIter,PositionAccessor,AllowContent,PositionStatus};
+
+/// Function of `LayerRule::Custom`
+pub type LayerRuleFn<Bg> = Box<dyn Fn(&Bg) -> bool + Send + Sync>;
+
+/// Where the contents of a `Layer` can be put.
+pub enum LayerRule<Bg> {
+    /// Only where the `Bg` allows contents, as in `Map`
+    FollowBg,
+    /// On any position of the map
+    Anywhere,
+    /// Where this function returns `true` for the `Bg`
+    Custom(LayerRuleFn<Bg>)
+}
+
+impl<Bg> LayerRule<Bg> {
+    /// `LayerRule::Custom` from a closure, which may capture some settings of the game
+    pub fn custom<F>(allows:F) -> LayerRule<Bg> where F : Fn(&Bg) -> bool + Send + Sync + 'static {
+        LayerRule::Custom(Box::new(allows))
+    }
+}
+
+impl<Bg> LayerRule<Bg> where Bg : AllowContent {
+    /// Whether a content can be put on a position with this `Bg`
+    pub fn allows(&self,bg:&Bg) -> bool {
+        match *self {
+            LayerRule::FollowBg => bg.is_content_allowed(),
+            LayerRule::Anywhere => true,
+            LayerRule::Custom(ref allows) => allows(bg),
+        }
+    }
+}
+
+/// One layer of a `LayeredMap`, holding at most one content per position.
+pub struct Layer<T,Bg> {
+    contents : Vec<Option<T>>,
+    rule : LayerRule<Bg>
+}
+
+impl<T,Bg> Layer<T,Bg> {
+    /// Creates an empty layer, which is sized when given to `LayeredMap::with_shape`.
+    pub fn new(rule:LayerRule<Bg>) -> Layer<T,Bg> {
+        Layer {
+            contents:Vec::new(),
+            rule
+        }
+    }
+
+    pub fn rule(&self) -> &LayerRule<Bg> {
+        &self.rule
+    }
+}
+
+/// Set of layers of a `LayeredMap`.
+///
+/// It is implemented for tuples of up to 8 `Layer`s, and can be implemented for a struct
+/// of layers to access them by name.
+pub trait LayerSet<Bg> {
+    /// Gives `len` empty positions to every layer
+    fn resize(&mut self,len:usize);
+}
+
+impl<T,Bg> LayerSet<Bg> for Layer<T,Bg> {
+    fn resize(&mut self,len:usize) {
+        self.contents.clear();
+        self.contents.resize_with(len,|| None);
+    }
+}
+
+macro_rules! layer_set_tuple {
+    ($($layer:ident $index:tt),+) => {
+        impl<Bg,$($layer),+> LayerSet<Bg> for ($($layer,)+) where $($layer : LayerSet<Bg>),+ {
+            fn resize(&mut self,len:usize) {
+                $(self.$index.resize(len);)+
+            }
+        }
+    }
+}
+
+layer_set_tuple!(A 0);
+layer_set_tuple!(A 0,B 1);
+layer_set_tuple!(A 0,B 1,C 2);
+layer_set_tuple!(A 0,B 1,C 2,D 3);
+layer_set_tuple!(A 0,B 1,C 2,D 3,E 4);
+layer_set_tuple!(A 0,B 1,C 2,D 3,E 4,F 5);
+layer_set_tuple!(A 0,B 1,C 2,D 3,E 4,F 5,G 6);
+layer_set_tuple!(A 0,B 1,C 2,D 3,E 4,F 5,G 6,H 7);
+
+/// A map made of several layers of contents (units, items, effects...) over a single `Bg`
+/// per position.
+///
+/// Layers are reached through a selector, such as `|layers| &layers.1` for the second
+/// layer of a tuple, which gives a view of this layer with the same API as `Map`: each
+/// layer holds at most one content per position, and where it can be put depends on the
+/// `LayerRule` of the layer.
+///
+/// ```
+/// use hexgrid::{LayeredMap,Layer,LayerRule,AllowContent,PositionAccessor};
+/// use hexgrid::pos::Position;
+///
+/// struct Unit { position:Position }
+/// struct Item { position:Position }
+/// # impl PositionAccessor for Unit {
+/// #     fn set_position(&mut self,new_position:Position) { self.position = new_position; }
+/// #     fn get_position(&self) -> Position { self.position }
+/// # }
+/// # impl PositionAccessor for Item {
+/// #     fn set_position(&mut self,new_position:Position) { self.position = new_position; }
+/// #     fn get_position(&self) -> Position { self.position }
+/// # }
+/// #[derive(Default)]
+/// struct Ground { water:bool }
+/// impl AllowContent for Ground {
+///     fn is_content_allowed(&self) -> bool { !self.water }
+/// }
+///
+/// let layers = (Layer::<Unit,Ground>::new(LayerRule::FollowBg),Layer::<Item,Ground>::new(LayerRule::Anywhere));
+/// let mut map = LayeredMap::new((4,4),Position::new(0,0),layers).unwrap();
+/// let position = Position::new(1,2);
+/// map.get_bg_mut(position).unwrap().water = true;
+/// assert!(map.layer_mut(|layers| &mut layers.0).create_content(position,Unit {position}).is_err());
+/// map.layer_mut(|layers| &mut layers.1).create_content(position,Item {position}).unwrap();
+/// assert_eq!(map.zip2(|layers| &layers.0,|layers| &layers.1).filter(|&(_,_,_,item)| item.is_some()).count(),1);
+/// ```
+pub struct LayeredMap<L : LayerSet<Bg>,Bg : Default + AllowContent> {
+    layers : L,
+    bg_slice : Box<[Bg]>,
+    shape : MapShape,
+    offset : Position
+}
+
+impl<L,Bg> LayeredMap<L,Bg> where L : LayerSet<Bg>, Bg : Default + AllowContent {
+    /// Creates a map shaped as a parallelogram, see `MapShape::Parallelogram`.
+    pub fn new(length:(i32,i32),offset:Position,layers:L) -> Result<LayeredMap<L,Bg>> {
+        Self::with_shape(MapShape::Parallelogram {width:length.0,height:length.1},offset,layers)
+    }
+
+    /// Creates a map with the given layers, whose previous contents are dropped.
+    pub fn with_shape(shape:MapShape,offset:Position,mut layers:L) -> Result<LayeredMap<L,Bg>> {
+        shape.validate()?;
+        let total_len = shape.len();
+        layers.resize(total_len);
+        let mut bgs = Vec::with_capacity(total_len);
+        for _i in 0 .. total_len {
+            bgs.push(Bg::default());
+        }
+        Ok(LayeredMap {
+            layers,
+            bg_slice:bgs.into_boxed_slice(),
+            shape,
+            offset
+        })
+    }
+
+    pub fn shape(&self) -> MapShape {
+        self.shape
+    }
+
+    pub fn offset(&self) -> Position {
+        self.offset
+    }
+
+    /// Whether `position` is part of the map
+    pub fn contains(&self,position:Position) -> bool {
+        self.shape.contains(position - self.offset)
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg(&self,position:Position) -> Result<&Bg> {
+        let index = self.shape.pos_to_index(position - self.offset)?;
+        Ok(&self.bg_slice[index])
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg_mut(&mut self,position:Position) -> Result<&mut Bg> {
+        let index = self.shape.pos_to_index(position - self.offset)?;
+        Ok(&mut self.bg_slice[index])
+    }
+
+    pub fn iter_bg(&self) -> MapIter<Iter<'_,Bg>> {
+        MapIter::new(self.bg_slice.iter(),self.shape,self.offset)
+    }
+
+    pub fn iter_bg_mut(&mut self) -> MapIter<IterMut<'_,Bg>> {
+        MapIter::new(self.bg_slice.iter_mut(),self.shape,self.offset)
+    }
+
+    /// View of the layer returned by `select`
+    pub fn layer<T,F>(&self,select:F) -> LayerRef<'_,T,Bg> where F : FnOnce(&L) -> &Layer<T,Bg> {
+        LayerRef {
+            layer:select(&self.layers),
+            bgs:&self.bg_slice,
+            shape:self.shape,
+            offset:self.offset
+        }
+    }
+
+    /// Mutable view of the layer returned by `select`
+    pub fn layer_mut<T,F>(&mut self,select:F) -> LayerMut<'_,T,Bg> where F : FnOnce(&mut L) -> &mut Layer<T,Bg> {
+        LayerMut {
+            layer:select(&mut self.layers),
+            bgs:&self.bg_slice,
+            shape:self.shape,
+            offset:self.offset
+        }
+    }
+
+    /// Every position with its `Bg` and its content in the two selected layers
+    pub fn zip2<'a,A : 'a,B : 'a,FA,FB>(&'a self,select_a:FA,select_b:FB) -> impl Iterator<Item=(Position,&'a Bg,Option<&'a A>,Option<&'a B>)> + 'a
+    where FA : FnOnce(&'a L) -> &'a Layer<A,Bg>, FB : FnOnce(&'a L) -> &'a Layer<B,Bg> {
+        let a = select_a(&self.layers).contents.iter();
+        let b = select_b(&self.layers).contents.iter();
+        MapIter::new(self.bg_slice.iter().zip(a).zip(b),self.shape,self.offset)
+            .map(|(position,((bg,a),b))| (position,bg,a.as_ref(),b.as_ref()))
+    }
+
+    /// Every position with its `Bg` and its content in the three selected layers
+    pub fn zip3<'a,A : 'a,B : 'a,C : 'a,FA,FB,FC>(&'a self,select_a:FA,select_b:FB,select_c:FC)
+    -> impl Iterator<Item=(Position,&'a Bg,Option<&'a A>,Option<&'a B>,Option<&'a C>)> + 'a
+    where FA : FnOnce(&'a L) -> &'a Layer<A,Bg>, FB : FnOnce(&'a L) -> &'a Layer<B,Bg>, FC : FnOnce(&'a L) -> &'a Layer<C,Bg> {
+        let a = select_a(&self.layers).contents.iter();
+        let b = select_b(&self.layers).contents.iter();
+        let c = select_c(&self.layers).contents.iter();
+        MapIter::new(self.bg_slice.iter().zip(a).zip(b).zip(c),self.shape,self.offset)
+            .map(|(position,(((bg,a),b),c))| (position,bg,a.as_ref(),b.as_ref(),c.as_ref()))
+    }
+}
+
+fn layer_status<T,Bg>(layer:&Layer<T,Bg>,bg:&Bg,index:usize) -> PositionStatus where Bg : AllowContent {
+    match layer.contents[index] {
+        Some(_) => PositionStatus::Busy,
+        None if layer.rule.allows(bg) => PositionStatus::Empty,
+        None => PositionStatus::Forbidden,
+    }
+}
+
+/// View of a layer of a `LayeredMap`, see `LayeredMap::layer`
+pub struct LayerRef<'a,T : 'a,Bg : 'a> {
+    layer : &'a Layer<T,Bg>,
+    bgs : &'a [Bg],
+    shape : MapShape,
+    offset : Position
+}
+
+impl<'a,T,Bg> LayerRef<'a,T,Bg> where Bg : AllowContent {
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get(&self,position:Position) -> Result<Option<&'a T>> {
+        let index = self.shape.pos_to_index(position - self.offset)?;
+        Ok(self.layer.contents[index].as_ref())
+    }
+
+    /// Status of `position` in this layer, following its `LayerRule`
+    pub fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        let index = self.shape.pos_to_index(position - self.offset)?;
+        Ok(layer_status(self.layer,&self.bgs[index],index))
+    }
+
+    /// Number of positions holding a content in this layer
+    pub fn contents_count(&self) -> usize {
+        self.layer.contents.iter().filter(|content| content.is_some()).count()
+    }
+
+    pub fn iter(&self) -> MapIter<Iter<'a,Option<T>>> {
+        MapIter::new(self.layer.contents.iter(),self.shape,self.offset)
+    }
+}
+
+/// Mutable view of a layer of a `LayeredMap`, see `LayeredMap::layer_mut`
+pub struct LayerMut<'a,T : 'a,Bg : 'a> {
+    layer : &'a mut Layer<T,Bg>,
+    bgs : &'a [Bg],
+    shape : MapShape,
+    offset : Position
+}
+
+impl<'a,T,Bg> LayerMut<'a,T,Bg> where T : PositionAccessor, Bg : AllowContent {
+    fn pos_to_index(&self,position:Position) -> Result<usize> {
+        self.shape.pos_to_index(position - self.offset)
+    }
+
+    fn check_empty(&self,index:usize) -> Result<()> {
+        match layer_status(self.layer,&self.bgs[index],index) {
+            PositionStatus::Empty => Ok(()),
+            PositionStatus::Busy => Err(Error::new(Reason::AlreadyOccupied)),
+            PositionStatus::Forbidden => Err(Error::new(Reason::ForbiddenLocation)),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get(&self,position:Position) -> Result<Option<&T>> {
+        let index = self.pos_to_index(position)?;
+        Ok(self.layer.contents[index].as_ref())
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_mut(&mut self,position:Position) -> Result<Option<&mut T>> {
+        let index = self.pos_to_index(position)?;
+        Ok(self.layer.contents[index].as_mut())
+    }
+
+    /// Status of `position` in this layer, following its `LayerRule`
+    pub fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        let index = self.pos_to_index(position)?;
+        Ok(layer_status(self.layer,&self.bgs[index],index))
+    }
+
+    /// Replace a `Position` with a new content.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `MissingTarget` if Position has no content (`None`)
+    pub fn replace_content(&mut self,position:Position,mut new_content:T) -> Result<T> {
+        let index = self.pos_to_index(position)?;
+        match self.layer.contents[index] {
+            Some(ref mut content) => {
+                new_content.set_position(position);
+                Ok(::std::mem::replace(content,new_content))
+            },
+            None => Err(Error::new(Reason::MissingTarget)),
+        }
+    }
+
+    /// Extract a content at `Position` and replace it with `None`.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `MissingTarget` if Position has no content (`None`)
+    pub fn extract_content(&mut self,position:Position) -> Result<T> {
+        let index = self.pos_to_index(position)?;
+        self.layer.contents[index].take().ok_or(Error::new(Reason::MissingTarget))
+    }
+
+    /// Put a new content at `Position`.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `AlreadyOccupied` if Position is busy in this layer
+    /// * `ForbiddenLocation` if the `LayerRule` does not allow contents at Position
+    pub fn create_content(&mut self,position:Position,mut new_content:T) -> Result<()> {
+        let index = self.pos_to_index(position)?;
+        self.check_empty(index)?;
+        new_content.set_position(position);
+        self.layer.contents[index] = Some(new_content);
+        Ok(())
+    }
+
+    /// Swap 2 elements.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if one or more positions is not valid
+    /// * `MissingTarget` if one the 2 position has no content (`None`)
+    pub fn swap_contents(&mut self,pos_1:Position,pos_2:Position) -> Result<()> {
+        let index_1 = self.pos_to_index(pos_1)?;
+        let index_2 = self.pos_to_index(pos_2)?;
+        let contents = &mut self.layer.contents;
+        if contents[index_1].is_none() || contents[index_2].is_none() {
+            return Err(Error::new(Reason::MissingTarget));
+        }
+        contents.swap(index_1,index_2);
+        contents[index_1].as_mut().unwrap().set_position(pos_1);
+        contents[index_2].as_mut().unwrap().set_position(pos_2);
+        Ok(())
+    }
+
+    /// Move an element from a position to another
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if one or more positions are not valid
+    /// * `MissingTarget` if the initial position has no element
+    /// * `AlreadyOccupied` if the final position is busy in this layer
+    /// * `ForbiddenLocation` if the `LayerRule` does not allow contents at the final position
+    pub fn move_contents(&mut self,from:Position,to:Position) -> Result<()> {
+        let index_from = self.pos_to_index(from)?;
+        let index_to = self.pos_to_index(to)?;
+        self.check_empty(index_to)?;
+        let mut content = self.layer.contents[index_from].take().ok_or(Error::new(Reason::MissingTarget))?;
+        content.set_position(to);
+        self.layer.contents[index_to] = Some(content);
+        Ok(())
+    }
+
+    /// Number of positions holding a content in this layer
+    pub fn contents_count(&self) -> usize {
+        self.layer.contents.iter().filter(|content| content.is_some()).count()
+    }
+
+    pub fn iter(&self) -> MapIter<Iter<'_,Option<T>>> {
+        MapIter::new(self.layer.contents.iter(),self.shape,self.offset)
+    }
+
+    pub fn iter_mut(&mut self) -> MapIter<IterMut<'_,Option<T>>> {
+        MapIter::new(self.layer.contents.iter_mut(),self.shape,self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::tests::{Dummy,Bg};
+
+    type Layers = (Layer<Dummy,Bg>,Layer<Dummy,Bg>,Layer<Dummy,Bg>);
+
+    fn dummy(name:&str) -> Dummy {
+        Dummy {pos:Position::default(),name:String::from(name)}
+    }
+
+    fn sample_map() -> LayeredMap<Layers,Bg> {
+        let kind = String::from("Swamp");
+        let only_swamps = LayerRule::custom(move |bg:&Bg| bg.kind == kind);
+        let layers = (Layer::new(LayerRule::FollowBg),Layer::new(LayerRule::Anywhere),Layer::new(only_swamps));
+        let mut map : LayeredMap<Layers,Bg> = LayeredMap::with_shape(MapShape::Hexagon {radius:2},Position::default(),layers).unwrap();
+        map.get_bg_mut(Position::new(1,0)).unwrap().kind = String::from("Obstacle");
+        map.get_bg_mut(Position::new(0,1)).unwrap().kind = String::from("Swamp");
+        map
+    }
+
+    #[test]
+    fn rules(){
+        let mut map = sample_map();
+        let (obstacle,swamp,plain) = (Position::new(1,0),Position::new(0,1),Position::new(-1,0));
+        {
+            let mut units = map.layer_mut(|layers| &mut layers.0);
+            assert_eq!(units.create_content(obstacle,dummy("unit")).unwrap_err(),Error::new(Reason::ForbiddenLocation));
+            units.create_content(plain,dummy("unit")).unwrap();
+            assert_eq!(units.create_content(plain,dummy("unit")).unwrap_err(),Error::new(Reason::AlreadyOccupied));
+            units.move_contents(plain,swamp).unwrap();
+            assert_eq!(units.get(swamp).unwrap().unwrap().get_position(),swamp);
+        }
+        map.layer_mut(|layers| &mut layers.1).create_content(obstacle,dummy("item")).unwrap();
+        map.layer_mut(|layers| &mut layers.1).create_content(swamp,dummy("item")).unwrap();
+        {
+            let mut effects = map.layer_mut(|layers| &mut layers.2);
+            assert_eq!(effects.create_content(plain,dummy("effect")).unwrap_err(),Error::new(Reason::ForbiddenLocation));
+            effects.create_content(swamp,dummy("effect")).unwrap();
+        }
+        assert_eq!(map.layer(|layers| &layers.0).position_status(obstacle).unwrap(),PositionStatus::Forbidden);
+        assert_eq!(map.layer(|layers| &layers.1).position_status(obstacle).unwrap(),PositionStatus::Busy);
+        assert_eq!(map.layer(|layers| &layers.1).contents_count(),2);
+        assert_eq!(map.layer(|layers| &layers.2).get(Position::new(3,0)).unwrap_err(),Error::new(Reason::OutOfRange));
+        assert_eq!(map.layer_mut(|layers| &mut layers.1).extract_content(obstacle).unwrap().name,"item");
+        assert_eq!(map.layer_mut(|layers| &mut layers.1).extract_content(obstacle).unwrap_err(),Error::new(Reason::MissingTarget));
+    }
+
+    #[test]
+    fn zip(){
+        let mut map = sample_map();
+        let swamp = Position::new(0,1);
+        map.layer_mut(|layers| &mut layers.0).create_content(swamp,dummy("unit")).unwrap();
+        map.layer_mut(|layers| &mut layers.1).create_content(Position::new(-2,0),dummy("item")).unwrap();
+        map.layer_mut(|layers| &mut layers.2).create_content(swamp,dummy("effect")).unwrap();
+        assert_eq!(map.zip2(|layers| &layers.0,|layers| &layers.1).count(),19);
+        let stacked : Vec<(Position,&str)> = map.zip3(|layers| &layers.0,|layers| &layers.1,|layers| &layers.2)
+            .filter_map(|(position,bg,unit,_,effect)| unit.and(effect).map(|_| (position,bg.kind.as_str())))
+            .collect();
+        assert_eq!(stacked,vec![(swamp,"Swamp")]);
+        let items : Vec<Position> = map.zip2(|layers| &layers.2,|layers| &layers.1)
+            .filter(|&(_,_,_,item)| item.is_some())
+            .map(|(position,_,_,_)| position)
+            .collect();
+        assert_eq!(items,vec![Position::new(-2,0)]);
+    }
+}
@@ -0,0 +1,652 @@
+use error::{Error,Reason,Result};
+use pos::Position;
+use std::vec::Vec ;
+use std::iter::{Iterator,Zip};
+use std::slice::{Iter,IterMut};
+
+pub use self::path::{BusyPolicy,Reachable};
+pub use self::shape::{MapShape,Wrap};
+pub use self::chunked::{ChunkedMap,DEFAULT_SEARCH_LIMIT};
+pub use self::sparse::SparseMap;
+pub use self::stack::{StackMap,Capacity,CapacityFn};
+pub use self::entity::{EntityMap,Handle,Occupant};
+pub use self::footprint::{FootprintMap,Footprint};
+pub use self::transaction::{Transaction,Operation,TransactionError};
+pub use self::journal::JournaledMap;
+pub use self::layered::{LayeredMap,Layer,LayerRule,LayerRuleFn,LayerSet,LayerRef,LayerMut};
+pub use self::binary::{Codec,Encoder,Decoder,MAP_FORMAT_VERSION};
+pub use self::hex_map::{HexMap,HexMapIter};
+
+mod shape;
+mod chunked;
+mod sparse;
+mod stack;
+mod layered;
+mod entity;
+mod footprint;
+mod transaction;
+mod journal;
+mod binary;
+mod ascii;
+mod hex_map;
+mod path;
+mod fov;
+#[cfg(feature="serde")]
+mod serialization;
+
+pub trait PositionAccessor {
+    fn set_position(&mut self,new_position:Position);
+    fn get_position(&self) -> Position ;
+}
+
+pub trait AllowContent {
+    fn is_content_allowed(&self) -> bool ;
+}
+
+pub struct MapIter<I>{
+    iter:I,
+    current_index:usize,
+    shape: MapShape,
+    offset: Position
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+pub enum PositionStatus {
+    /// Bg has position allowed and no element is at this Position
+    Empty,
+    /// Bg has position allowed but an element is already at this position
+    Busy,
+    /// Bg does not allow content at this Position
+    Forbidden
+}
+
+impl<I> Iterator for MapIter<I> where I : Iterator {
+    type Item = (Position,<I as Iterator>::Item) ;
+    #[inline]
+    fn next(&mut self) -> Option<(Position, <I as Iterator>::Item)> {
+        let position = self.shape.index_to_pos(self.current_index);
+        match position {
+            Ok(position) => self.iter.next().map(|a| {
+                let position = position + self.offset;
+                let ret = (position, a);
+                self.current_index += 1;
+                ret
+            }),
+            Err(err) if err == Error::new(Reason::OutOfRange) => None,
+            Err(_) => unreachable!()
+        }
+    }
+}
+
+impl<I> MapIter<I> where I : Iterator {
+    pub fn new(iter:I,shape: MapShape,offset: Position) -> MapIter<I> {
+        MapIter {
+            current_index:0,
+            iter,
+            shape,
+            offset
+        }
+    }
+}
+
+pub struct Map<T : PositionAccessor,Bg : Default + AllowContent > {
+    contents_slice : Box<[Option<T>]>,
+    bg_slice : Box<[Bg]>,
+    shape: MapShape,
+    offset: Position,
+    wrap: Wrap
+}
+
+impl<T,Bg> Map<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    /// Creates a map shaped as a parallelogram, see `MapShape::Parallelogram`.
+    pub fn new(length:(i32,i32),offset:Position) -> Result<Map<T,Bg>> {
+        Self::with_shape(MapShape::Parallelogram {width:length.0,height:length.1},offset)
+    }
+
+    pub fn with_shape(shape:MapShape,offset:Position) -> Result<Map<T,Bg>> {
+        Self::with_wrap(shape,offset,Wrap::None)
+    }
+
+    /// Creates a map whose edges wrap around: positions outside of the shape are brought
+    /// back into it along the wrapped dimensions on every access, and `HexMap` distances,
+    /// neighbors, ranges, paths and fields of view go across the edges.
+    ///
+    /// # Errors
+    ///
+    /// * `NegativeMapLength` if a dimension of the shape is negative
+    /// * `InvalidTopology` if the shape cannot wrap this way, see `MapShape::periods`
+    pub fn with_wrap(shape:MapShape,offset:Position,wrap:Wrap) -> Result<Map<T,Bg>> {
+        shape.validate()?;
+        shape.periods(wrap)?;
+        let total_len : usize = shape.len();
+        let mut contents_vec : Vec<Option<T>> = Vec::with_capacity(total_len);
+        let mut bg_vec : Vec<Bg> = Vec::with_capacity(total_len);
+        for _i in 0 .. total_len {
+            contents_vec.push(None);
+            bg_vec.push(Bg::default());
+        };
+        Ok(Map::<T,Bg> {
+            contents_slice:contents_vec.into_boxed_slice(),
+            bg_slice:bg_vec.into_boxed_slice(),
+            shape,
+            offset,
+            wrap
+        })
+    }
+
+    pub fn shape(&self) -> MapShape {
+        self.shape
+    }
+
+    pub fn wrap(&self) -> Wrap {
+        self.wrap
+    }
+
+    pub fn offset(&self) -> Position {
+        self.offset
+    }
+
+    /// Whether `position` is part of the map, once wrapped around
+    pub fn contains(&self,position:Position) -> bool {
+        self.pos_to_index(position).is_ok()
+    }
+
+    pub fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        let index = self.pos_to_index(position)?;
+        let (contents, bg) = self.get_unchecked(index);
+        let result = match *contents {
+            Some(_) => PositionStatus::Busy,
+            None if bg.is_content_allowed() => PositionStatus::Empty,
+            None if !bg.is_content_allowed() => PositionStatus::Forbidden,
+            _ => unreachable!()
+        };
+        Ok(result)
+    }
+
+    /// Creates a map and fills it with `iter`.
+    ///
+    /// Positions can be given in any coordinate system convertible to `Position`, such as
+    /// `OffsetPosition` or `DoubledPosition`.
+    pub fn from_iter<I,P>(iter:I,length:(i32,i32),offset:Position) -> Result<Map<T,Bg>> where I : IntoIterator<Item=(P,(T,Bg))>, P : Into<Position> {
+        Self::from_iter_with_shape(iter,MapShape::Parallelogram {width:length.0,height:length.1},offset)
+    }
+
+    /// Same as `from_iter`, for any `MapShape`.
+    pub fn from_iter_with_shape<I,P>(iter:I,shape:MapShape,offset:Position) -> Result<Map<T,Bg>> where I : IntoIterator<Item=(P,(T,Bg))>, P : Into<Position> {
+        let mut map = Self::with_shape(shape,offset)?;
+        for (pos,(content,bg)) in iter {
+            let pos = pos.into();
+            map.create_content(pos,content)?;
+            let map_bg = map.get_bg_mut(pos).unwrap();
+            *map_bg = bg ;
+        }
+        Ok(map)
+    }
+
+    fn pos_to_index(&self,pos:Position) -> Result<usize> {
+        self.shape.pos_to_index(self.shape.wrap(pos - self.offset,self.wrap))
+    }
+
+    fn index_to_pos(&self,index:usize) -> Result<Position> {
+        self.shape.index_to_pos(index).map(|position| position + self.offset)
+    }
+
+    #[inline]
+    fn get_unchecked(&self,index:usize) -> (&Option<T>,&Bg) {
+        unsafe {
+            (self.contents_slice.get_unchecked(index),self.bg_slice.get_unchecked(index))
+        }
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_mut(&mut self,pos:Position) -> Result<(&mut Option<T>,&mut Bg)> {
+        let index = self.pos_to_index(pos)?;
+        Ok((&mut self.contents_slice[index],&mut self.bg_slice[index]))
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get(&self,pos:Position) -> Result<(&Option<T>,&Bg)> {
+        let index = self.pos_to_index(pos)?;
+        Ok((&self.contents_slice[index],&self.bg_slice[index]))
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_contents(&self,pos:Position) -> Result<&Option<T>> {
+        let index = self.pos_to_index(pos)?;
+        Ok(&self.contents_slice[index])
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_contents_mut(&mut self,pos:Position) -> Result<&mut Option<T>> {
+        let index = self.pos_to_index(pos)?;
+        Ok(&mut self.contents_slice[index])
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg(&self,pos:Position) -> Result<&Bg> {
+        let index = self.pos_to_index(pos)?;
+        Ok(&self.bg_slice[index])
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg_mut(&mut self,pos:Position) -> Result<&mut Bg>{
+        let index = self.pos_to_index(pos)?;
+        Ok(&mut self.bg_slice[index])
+    }
+
+    /// Replace a `Position` with a new content.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `MissingTarget` if Position has no content (`None`)
+    pub fn replace_content(&mut self,position:Position,mut new_content:T) -> Result<T> {
+        let index = self.pos_to_index(position)?;
+        if self.contents_slice[index].is_some() {
+            new_content.set_position(self.index_to_pos(index)?);
+            let replaced = self.contents_slice[index].replace(new_content);
+            Ok(replaced.expect("Unexpected None"))
+        } else {
+            Err(Error::new(Reason::MissingTarget))
+        }
+    }
+
+    /// Extract a content at `Position` and replace it with `None`.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `MissingTarget` if Position has no content (`None`)
+    pub fn extract_content(&mut self,position:Position) -> Result<T> {
+        let index = self.pos_to_index(position)?;
+        if self.contents_slice[index].is_some() {
+            Ok(self.contents_slice[index].take().unwrap())
+        } else {
+            Err(Error::new(Reason::MissingTarget))
+        }
+    }
+
+    /// Extract a content at `Position` and replace it with `None`.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `AlreadyOccupied` if Position
+    pub fn create_content(&mut self,position:Position,mut new_content:T) -> Result<()> {
+        let index = self.pos_to_index(position)?;
+        let position = self.index_to_pos(index)?;
+        match self.position_status(position) {
+            Ok(PositionStatus::Empty) => Ok(()),
+            Ok(PositionStatus::Busy) => Err(Error::new(Reason::AlreadyOccupied)),
+            Ok(PositionStatus::Forbidden) => Err(Error::new(Reason::ForbiddenLocation)),
+            Err(_) => unreachable!()
+        }?;
+        let content = &mut self.contents_slice[index];
+        match *content {
+            None => {
+                new_content.set_position(position);
+                *content = Some(new_content);
+                Ok(())
+            },
+            Some(_) => {
+                Err(Error::new(Reason::AlreadyOccupied))
+            }
+        }
+    }
+
+    /// Swap 2 elements.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if one or more positions is not valid
+    /// * `MissingTarget` if one the 2 position has no content (`None`)
+    pub fn swap_contents(&mut self,pos_1:Position,pos_2:Position) -> Result<()> {
+        let index_1 = self.pos_to_index(pos_1)?;
+        let index_2 = self.pos_to_index(pos_2)?;
+        let missing_target : bool = {
+            let content_1 = &self.contents_slice[index_1];
+            let content_2 = &self.contents_slice[index_2];
+            content_1.is_none() || content_2.is_none()
+        };
+        if missing_target {
+            Err(Error::new(Reason::MissingTarget))
+        } else {
+            self.contents_slice.swap(index_1,index_2);
+            let (pos_1,pos_2) = (self.index_to_pos(index_1)?,self.index_to_pos(index_2)?);
+            self.contents_slice[index_1].as_mut().unwrap().set_position(pos_1);
+            self.contents_slice[index_2].as_mut().unwrap().set_position(pos_2);
+            Ok(())
+        }
+    }
+
+    /// Move an element from a position to another
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if one or more positions are not valid
+    /// * `MissingTarget` if the initial position has no element
+    /// * `AlreadyOccupied` if the final position is busy
+    pub fn move_contents(&mut self,from:Position,to:Position) -> Result<()> {
+        let index_from = self.pos_to_index(from)?;
+        let index_to = self.pos_to_index(to)?;
+        let status_to = self.position_status(to)?;
+        match status_to {
+            PositionStatus::Empty => Ok(()),
+            PositionStatus::Busy => Err(Error::new(Reason::AlreadyOccupied)),
+            PositionStatus::Forbidden => Err(Error::new(Reason::ForbiddenLocation ))
+        }?;
+        if self.contents_slice[index_from].is_none() {
+            Err(Error::new(Reason::MissingTarget))
+        } else if self.contents_slice[index_to].is_some() {
+            Err(Error::new(Reason::AlreadyOccupied))
+        } else {
+            self.contents_slice.swap(index_from,index_to);
+            let to = self.index_to_pos(index_to)?;
+            self.contents_slice[index_to].as_mut().unwrap().set_position(to);
+            Ok(())
+        }
+    }
+
+    pub fn iter_contents(&self) -> MapIter<Iter<'_,Option<T>>> {
+        MapIter::new(self.contents_slice.iter(),self.shape, self.offset)
+    }
+
+    pub fn iter_contents_mut(&mut self) -> MapIter<IterMut<'_,Option<T>>> {
+        MapIter::new(self.contents_slice.iter_mut(),self.shape, self.offset)
+    }
+
+    pub fn iter_bg(&self) -> MapIter<Iter<'_,Bg>> {
+        MapIter::new(self.bg_slice.iter(),self.shape, self.offset)
+    }
+
+    pub fn iter_bg_mut(&mut self) -> MapIter<IterMut<'_,Bg>> {
+        MapIter::new(self.bg_slice.iter_mut(),self.shape, self.offset)
+    }
+
+    pub fn iter(&self) -> MapIter<Zip<Iter<'_,Option<T>>,Iter<'_,Bg>>> {
+        let zipped_iter = self.contents_slice.iter().zip(self.bg_slice.iter()) ;
+        MapIter::new(zipped_iter,self.shape, self.offset)
+    }
+
+    pub fn iter_mut(&mut self) -> MapIter<Zip<IterMut<'_,Option<T>>,IterMut<'_,Bg>>> {
+        let zipped_iter = self.contents_slice.iter_mut().zip(self.bg_slice.iter_mut()) ;
+        MapIter::new(zipped_iter,self.shape, self.offset)
+    }
+}
+
+impl<T,Bg> HexMap for Map<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    type Content = T ;
+    type Bg = Bg ;
+
+    fn get(&self,position:Position) -> Result<(Option<&T>,&Bg)> {
+        Map::get(self,position).map(|(content,bg)| (content.as_ref(),bg))
+    }
+
+    fn get_mut(&mut self,position:Position) -> Result<(Option<&mut T>,&mut Bg)> {
+        Map::get_mut(self,position).map(|(content,bg)| (content.as_mut(),bg))
+    }
+
+    fn contains(&self,position:Position) -> bool {
+        Map::contains(self,position)
+    }
+
+    fn iter<'a>(&'a self) -> HexMapIter<'a,T,Bg> {
+        Box::new(Map::iter(self).map(|(position,(content,bg))| (position,content.as_ref(),bg)))
+    }
+
+    fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        Map::position_status(self,position)
+    }
+
+    fn canonicalize(&self,position:Position) -> Result<Position> {
+        let index = self.pos_to_index(position)?;
+        self.index_to_pos(index)
+    }
+
+    fn nearest_image(&self,from:Position,to:Position) -> Position {
+        let (horizontal,vertical) = self.shape.periods(self.wrap).expect("Checked on creation");
+        let horizontal = horizontal.unwrap_or_default();
+        let vertical = vertical.unwrap_or_default();
+        let mut nearest = to;
+        for i in -1..2 {
+            for j in -1..2 {
+                let image = to + horizontal * i + vertical * j;
+                if from.distance_to(image) < from.distance_to(nearest) {
+                    nearest = image;
+                }
+            }
+        }
+        nearest
+    }
+
+    fn find_path<F>(&self,from:Position,to:Position,busy:BusyPolicy,cost_fn:F) -> Option<(Vec<Position>,u32)>
+    where F : FnMut(&Bg) -> Option<u32> {
+        Map::find_path(self,from,to,busy,cost_fn)
+    }
+
+    fn reachable<F>(&self,from:Position,budget:u32,busy:BusyPolicy,cost_fn:F) -> Result<Reachable>
+    where F : FnMut(&Bg) -> Option<u32> {
+        Map::reachable(self,from,budget,busy,cost_fn)
+    }
+}
+
+#[test]
+pub fn test_pos_to_index(){
+    let m = self::tests::sample_map();
+    assert_eq!(m.pos_to_index(Position::new(-5,-5)).unwrap(),
+               0);
+    assert_eq!(m.pos_to_index(Position::new(-5,-4)).unwrap(),
+               10);
+    assert_eq!(m.pos_to_index(Position::new(-4,-4)).unwrap(),
+               11);
+    assert_eq!(m.pos_to_index(Position::new(4,4)).unwrap(),
+               99);
+    assert_eq!(m.pos_to_index(Position::new(4,5)).unwrap_err(),
+               Error::new(Reason::OutOfRange));
+    assert_eq!(m.pos_to_index(Position::new(5,0)).unwrap_err(),
+               Error::new(Reason::OutOfRange));
+    assert_eq!(m.pos_to_index(Position::new(-10,0)).unwrap_err(),
+               Error::new(Reason::OutOfRange));
+}
+
+#[test]
+pub fn test_index_to_pos(){
+    let m = self::tests::sample_map();
+    assert_eq!(m.index_to_pos(0).unwrap(),
+               Position::new(-5,-5));
+    assert_eq!(m.index_to_pos(11).unwrap(),
+               Position::new(-4,-4));
+    assert_eq!(m.index_to_pos(99).unwrap(),
+               Position::new(4,4));
+    assert_eq!(m.index_to_pos(100).unwrap_err(),
+               Error::new(Reason::OutOfRange));
+    assert_eq!(m.index_to_pos(150).unwrap_err(),
+               Error::new(Reason::OutOfRange));
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use pos::Position;
+    use error::*;
+    use std::string::String;
+    #[derive(Debug)]
+    pub struct Dummy {
+        pub pos:Position,
+        #[allow(dead_code)]
+        pub name:String
+    }
+
+    #[derive(Debug,Default,Clone,PartialEq)]
+    pub struct Bg {
+        pub kind:String
+    }
+
+    impl AllowContent for Bg {
+        fn is_content_allowed(&self) -> bool {
+            self.kind != "Obstacle"
+        }
+    }
+
+    impl PositionAccessor for Dummy {
+        fn set_position(&mut self,new_position:Position) {
+            self.pos = new_position;
+        }
+        fn get_position(&self) -> Position {
+            self.pos
+        }
+    }
+
+    pub fn sample_map() -> Map<Dummy,Bg> {
+        Map::new((10,10),Position::new(-5,-5)).unwrap()
+    }
+
+    #[test]
+    fn from_offset_iter(){
+        use pos::{OffsetPosition,OffsetKind};
+        let dummy = |col,row| {
+            (OffsetPosition::new(col,row,OffsetKind::OddR),
+             (Dummy{pos:Position::default(),name:String::from("dummy")},Bg::default()))
+        };
+        let map : Map<Dummy,Bg> = Map::from_iter(vec![dummy(0,0),dummy(1,1),dummy(0,3)],
+                                                 (10,10),Position::new(-5,-5)).unwrap();
+        for &(col,row) in &[(0,0),(1,1),(0,3)] {
+            let position = Position::from(OffsetPosition::new(col,row,OffsetKind::OddR));
+            let dummy = map.get_contents(position).unwrap().as_ref().unwrap();
+            assert_eq!(dummy.get_position(),position);
+        }
+    }
+
+    #[test]
+    fn hexagon_map(){
+        use map::MapShape;
+        let center = Position::new(2,-1);
+        let mut map : Map<Dummy,Bg> = Map::with_shape(MapShape::Hexagon {radius:2},center).unwrap();
+        assert_eq!(map.iter().count(),19);
+        let mut positions : Vec<Position> = map.iter_bg().map(|(position,_)| position).collect();
+        let mut expected = center.in_range(2);
+        positions.sort_by_key(|p| (p.x,p.y));
+        expected.sort_by_key(|p| (p.x,p.y));
+        assert_eq!(positions,expected);
+        assert!(map.contains(center + Position::new(2,-2)));
+        assert!(!map.contains(center + Position::new(2,1)));
+        assert_eq!(map.create_content(center + Position::new(2,1),
+                                      Dummy{pos:Position::default(),name:String::from("out")}).unwrap_err(),
+                   Error::new(Reason::OutOfRange));
+        map.create_content(center + Position::new(-2,2),Dummy{pos:Position::default(),name:String::from("in")}).unwrap();
+        let (position,dummy) = map.iter_contents().find(|(_,dummy)| dummy.is_some()).unwrap();
+        assert_eq!(position,center + Position::new(-2,2));
+        assert_eq!(dummy.as_ref().unwrap().get_position(),position);
+    }
+
+    #[test]
+    fn routine_test(){
+        let mut map : Map<Dummy,Bg> = sample_map();
+        let dummy_1 = Dummy{
+            pos:Position::default(),
+            name:String::from("test_dummy_1")
+        };
+        let dummy_2 = Dummy {
+            pos:Position::default(),
+            name:String::from("test_dummy_2")
+        };
+        let dummy_3 = Dummy {
+            pos:Position::default(),
+            name:String::from("test_dummy_3")
+        };
+        let dummy_4 = Dummy {
+            pos:Position::default(),
+            name:String::from("test_dummy_4")
+        };
+        map.create_content(Position::new(0,0),dummy_1).unwrap();
+        map.create_content(Position::new(2,0),dummy_2).unwrap();
+        assert_eq!(map.get_contents(Position::new(2,0)).unwrap().as_ref().unwrap().get_position(),
+                   Position::new(2,0));
+
+        let err = map.create_content(Position::new(2,0),dummy_3);
+        assert_eq!(err.unwrap_err(),
+                   Error::new(Reason::AlreadyOccupied));
+
+        map.get_bg_mut(Position::new(4,0)).unwrap().kind = String::from("Obstacle");
+
+        let err = map.create_content(Position::new(4,0),dummy_4);
+        assert_eq!(err.unwrap_err(),
+                   Error::new(Reason::ForbiddenLocation));
+
+        // create 2 dummies and swap their position
+        map.swap_contents(Position::new(2,0), Position::new(0,0)).unwrap();
+        for &position in &[Position::new(2,0),Position::new(0,0)] {
+            assert_eq!(map.get_contents(position).unwrap().as_ref().unwrap().get_position(),position);
+        }
+        assert_eq!(map.swap_contents(Position::new(3,0), Position::new(0,0)).unwrap_err(),
+                   Error::new(Reason::MissingTarget));
+        assert_eq!(map.iter_contents()
+                      .filter(|(_,dummy_option)| dummy_option.is_some())
+                      .count(),
+                   2);
+        // count 2 dummies
+        {
+            let iter = map.iter_mut().filter(|(_,(dummy,_))| dummy.is_some());
+            for (_pos,(opt, _bg)) in iter {
+                *opt = None ;
+                // delete 2 dummies
+            }
+        }
+        assert_eq!(map.iter_contents()
+                      .filter(|(_,dummy_option)| dummy_option.is_some())
+                      .count(),
+                   0);
+        // count 0 dummies
+    }
+
+    #[test]
+    fn wrap_around(){
+        let dummy = || Dummy{pos:Position::default(),name:String::from("dummy")};
+        let shape = MapShape::Parallelogram {width:10,height:10};
+        assert_eq!(Map::<Dummy,Bg>::with_wrap(MapShape::Hexagon {radius:3},Position::default(),Wrap::Horizontal).err(),
+                   Some(Error::new(Reason::InvalidTopology)));
+        let mut map : Map<Dummy,Bg> = Map::with_wrap(shape,Position::new(-5,-5),Wrap::Horizontal).unwrap();
+        let (east,west) = (Position::new(4,0),Position::new(-5,0));
+        assert!(map.contains(Position::new(5,0)));
+        assert!(!map.contains(Position::new(0,5)));
+        map.create_content(Position::new(5,0),dummy()).unwrap();
+        assert_eq!(map.get_contents(west).unwrap().as_ref().unwrap().get_position(),west);
+        map.move_contents(west,Position::new(15,1)).unwrap();
+        assert_eq!(map.get_contents(Position::new(-5,1)).unwrap().as_ref().unwrap().get_position(),
+                   Position::new(-5,1));
+
+        assert_eq!(map.canonicalize(Position::new(25,3)),Ok(Position::new(-5,3)));
+        assert_eq!(map.distance(east,west),1);
+        assert!(map.neighbors(east).contains(&west));
+        assert_eq!(map.neighbors(Position::new(4,4)).len(),4);
+        let (path,cost) = map.find_path(east,Position::new(-4,0),BusyPolicy::Blocked,|_| Some(1)).unwrap();
+        assert_eq!(path,vec![east,west,Position::new(-4,0)]);
+        assert_eq!(cost,2);
+        assert!(map.has_line_of_sight(Position::new(2,0),Position::new(-4,0),|_| false).unwrap());
+        map.get_bg_mut(west).unwrap().kind = String::from("Obstacle");
+        assert!(!map.has_line_of_sight(Position::new(2,0),Position::new(-4,0),|bg| bg.kind == "Obstacle").unwrap());
+        let visible = map.field_of_view(east,3,|bg| bg.kind == "Obstacle").unwrap();
+        assert!(visible.contains(&west));
+        assert!(!visible.contains(&Position::new(-3,0)));
+
+        let torus : Map<Dummy,Bg> = Map::with_wrap(shape,Position::new(-5,-5),Wrap::Both).unwrap();
+        assert_eq!(torus.distance(Position::new(0,4),Position::new(0,-5)),1);
+        assert_eq!(torus.in_range(Position::new(4,4),2).len(),19);
+        let everything = torus.in_range(Position::default(),20);
+        assert_eq!(everything.len(),100);
+        assert!(everything.iter().all(|&position| Map::contains(&torus,position)));
+        assert_eq!(torus.field_of_view(Position::default(),20,|_| false).unwrap().len(),100);
+    }
+}
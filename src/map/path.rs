@@ -0,0 +1,518 @@
+use std::collections::{BinaryHeap,HashMap};
+use std::cmp::Reverse;
+use std::slice::Iter;
+use error::Result;
+use pos::Position;
+use map::{Map,HexMap,PositionAccessor,AllowContent,PositionStatus};
+
+/// How path searches treat positions holding some content.
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum BusyPolicy {
+    /// `Busy` positions can never be entered
+    Blocked,
+    /// `Busy` positions are treated like `Empty` ones
+    Passable,
+    /// `Busy` positions can be entered only as the last step, e.g. to attack what is there
+    PassableAtGoal
+}
+
+impl BusyPolicy {
+    /// Whether `position`, which has `status`, may be entered; `goal` tells if it is the
+    /// target of the search.
+    pub(crate) fn allows(self,status:&PositionStatus,goal:bool) -> bool {
+        match *status {
+            PositionStatus::Empty => true,
+            PositionStatus::Forbidden => false,
+            PositionStatus::Busy => match self {
+                BusyPolicy::Blocked => false,
+                BusyPolicy::Passable => true,
+                BusyPolicy::PassableAtGoal => goal,
+            },
+        }
+    }
+}
+
+/// Every position reachable from an origin within a cost budget, see `HexMap::reachable`.
+#[derive(Clone,Debug)]
+pub struct Reachable {
+    origin:Position,
+    /// cost to reach a position, and the position it is reached from
+    nodes:HashMap<Position,(u32,Option<Position>)>,
+    /// reached positions, by increasing cost
+    order:Vec<Position>
+}
+
+impl Reachable {
+    pub fn origin(&self) -> Position {
+        self.origin
+    }
+
+    pub fn contains(&self,position:Position) -> bool {
+        self.nodes.contains_key(&position)
+    }
+
+    /// Cheapest cost to go from the origin to `position`, if it is reachable
+    pub fn cost(&self,position:Position) -> Option<u32> {
+        self.nodes.get(&position).map(|&(cost,_)| cost)
+    }
+
+    /// Position right before `position` on the cheapest path from the origin
+    pub fn predecessor(&self,position:Position) -> Option<Position> {
+        self.nodes.get(&position).and_then(|&(_,previous)| previous)
+    }
+
+    /// Cheapest path from the origin to `position`, both included
+    pub fn path_to(&self,position:Position) -> Option<Vec<Position>> {
+        if !self.contains(position) {
+            return None;
+        }
+        let mut path = vec![position];
+        let mut current = position;
+        while let Some(previous) = self.predecessor(current) {
+            path.push(previous);
+            current = previous;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Every reachable position (the origin included), by increasing cost
+    pub fn positions(&self) -> Iter<'_,Position> {
+        self.order.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+/// What a search knows about a position: the cheapest cost found to reach it, the
+/// position it is reached from, and whether it is done with
+#[derive(Copy,Clone,Debug)]
+pub(crate) struct Node {
+    cost:u32,
+    previous:Option<Position>,
+    done:bool
+}
+
+/// Bookkeeping of a search, by canonical position: a `HashMap` for any map, or vectors
+/// indexed like the positions of a `Map`.
+pub(crate) trait Nodes {
+    fn get(&self,position:Position) -> Option<&Node>;
+
+    fn get_mut(&mut self,position:Position) -> Option<&mut Node>;
+
+    fn insert(&mut self,position:Position,node:Node);
+}
+
+impl Nodes for HashMap<Position,Node> {
+    fn get(&self,position:Position) -> Option<&Node> {
+        HashMap::get(self,&position)
+    }
+
+    fn get_mut(&mut self,position:Position) -> Option<&mut Node> {
+        HashMap::get_mut(self,&position)
+    }
+
+    fn insert(&mut self,position:Position,node:Node) {
+        HashMap::insert(self,position,node);
+    }
+}
+
+/// `Nodes` of a search on a `Map`, one slot per position
+pub(crate) struct DenseNodes<'a,T : 'a + PositionAccessor,Bg : 'a + Default + AllowContent> {
+    map:&'a Map<T,Bg>,
+    nodes:Vec<Option<Node>>
+}
+
+impl<'a,T,Bg> DenseNodes<'a,T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    pub(crate) fn new(map:&'a Map<T,Bg>) -> DenseNodes<'a,T,Bg> {
+        DenseNodes {
+            map,
+            nodes:vec![None;map.contents_slice.len()]
+        }
+    }
+}
+
+impl<'a,T,Bg> Nodes for DenseNodes<'a,T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    fn get(&self,position:Position) -> Option<&Node> {
+        self.nodes[self.map.pos_to_index(position).ok()?].as_ref()
+    }
+
+    fn get_mut(&mut self,position:Position) -> Option<&mut Node> {
+        self.nodes[self.map.pos_to_index(position).ok()?].as_mut()
+    }
+
+    fn insert(&mut self,position:Position,node:Node) {
+        if let Ok(index) = self.map.pos_to_index(position) {
+            self.nodes[index] = Some(node);
+        }
+    }
+}
+
+/// Whether `nodes` holds `position` and is done with it
+fn is_done<N>(nodes:&N,position:Position) -> bool where N : Nodes {
+    nodes.get(position).is_some_and(|node| node.done)
+}
+
+/// See `HexMap::find_path`
+pub(crate) fn find_path<M,N,F>(map:&M,mut nodes:N,from:Position,to:Position,busy:BusyPolicy,mut cost_fn:F) -> Option<(Vec<Position>,u32)>
+where M : HexMap, N : Nodes, F : FnMut(&M::Bg) -> Option<u32> {
+    let (from,to) = match (map.canonicalize(from),map.canonicalize(to)) {
+        (Ok(from),Ok(to)) => (from,to),
+        _ => return None,
+    };
+    if from == to {
+        return Some((vec![from],0));
+    }
+    // (estimated total cost, estimated remaining cost, insertion order, x, y)
+    let mut open = BinaryHeap::new();
+    let mut counter : u64 = 0;
+    let mut expanded = 0;
+    let limit = map.search_limit();
+    nodes.insert(from,Node {cost:0,previous:None,done:false});
+    let heuristic = map.distance(from,to) as u32;
+    open.push(Reverse((heuristic,heuristic,counter,from.x,from.y)));
+    while let Some(Reverse((_,_,_,x,y))) = open.pop() {
+        let position = Position::new(x,y);
+        if position == to {
+            let mut path = vec![to];
+            let mut current = position;
+            while let Some(previous) = nodes.get(current).and_then(|node| node.previous) {
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            return nodes.get(to).map(|node| (path,node.cost));
+        }
+        let cost = match nodes.get_mut(position) {
+            Some(node) if !node.done => {
+                node.done = true;
+                node.cost
+            },
+            _ => continue,
+        };
+        expanded += 1;
+        if limit.is_some_and(|limit| expanded > limit) {
+            return None;
+        }
+        for neighbor in map.neighbors(position) {
+            if is_done(&nodes,neighbor) {
+                continue;
+            }
+            let status = map.position_status(neighbor).unwrap();
+            if !busy.allows(&status,neighbor == to) {
+                continue;
+            }
+            // a step costing more than `u32::MAX` in total cannot be taken
+            let new_cost = match cost_fn(map.get(neighbor).unwrap().1) {
+                Some(step_cost) => match cost.checked_add(step_cost.max(1)) {
+                    Some(new_cost) => new_cost,
+                    None => continue,
+                },
+                None => continue,
+            };
+            if nodes.get(neighbor).is_none_or(|node| new_cost < node.cost) {
+                nodes.insert(neighbor,Node {cost:new_cost,previous:Some(position),done:false});
+                let heuristic = map.distance(neighbor,to) as u32;
+                counter += 1;
+                open.push(Reverse((new_cost.saturating_add(heuristic),heuristic,counter,neighbor.x,neighbor.y)));
+            }
+        }
+    }
+    None
+}
+
+/// See `HexMap::reachable`
+pub(crate) fn reachable<M,N,F>(map:&M,mut nodes:N,from:Position,budget:u32,busy:BusyPolicy,mut cost_fn:F) -> Result<Reachable>
+where M : HexMap, N : Nodes, F : FnMut(&M::Bg) -> Option<u32> {
+    let from = map.canonicalize(from)?;
+    let mut order = Vec::new();
+    // (cost, insertion order, x, y)
+    let mut open = BinaryHeap::new();
+    let mut counter : u64 = 0;
+    let limit = map.search_limit();
+    nodes.insert(from,Node {cost:0,previous:None,done:false});
+    open.push(Reverse((0u32,counter,from.x,from.y)));
+    while let Some(Reverse((cost,_,x,y))) = open.pop() {
+        let position = Position::new(x,y);
+        if is_done(&nodes,position) {
+            continue;
+        }
+        if limit.is_some_and(|limit| order.len() >= limit) {
+            break;
+        }
+        nodes.get_mut(position).unwrap().done = true;
+        order.push(position);
+        if position != from && !busy.allows(&map.position_status(position).unwrap(),false) {
+            // only reachable as a goal
+            continue;
+        }
+        for neighbor in map.neighbors(position) {
+            if is_done(&nodes,neighbor) {
+                continue;
+            }
+            let status = map.position_status(neighbor).unwrap();
+            if !busy.allows(&status,true) {
+                continue;
+            }
+            let new_cost = match cost_fn(map.get(neighbor).unwrap().1).and_then(|step_cost| cost.checked_add(step_cost.max(1))) {
+                Some(new_cost) if new_cost <= budget => new_cost,
+                _ => continue,
+            };
+            if nodes.get(neighbor).is_none_or(|node| new_cost < node.cost) {
+                nodes.insert(neighbor,Node {cost:new_cost,previous:Some(position),done:false});
+                counter += 1;
+                open.push(Reverse((new_cost,counter,neighbor.x,neighbor.y)));
+            }
+        }
+    }
+    // only the settled positions, the ones found but not settled may not be the cheapest
+    let nodes = order.iter()
+        .map(|&position| {
+            let node = nodes.get(position).unwrap();
+            (position,(node.cost,node.previous))
+        })
+        .collect();
+    Ok(Reachable {
+        origin:from,
+        nodes,
+        order
+    })
+}
+
+impl<T,Bg> Map<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    /// Same as `HexMap::find_path`, with the bookkeeping of the search kept in vectors
+    /// indexed like the positions of the map.
+    pub fn find_path<F>(&self,from:Position,to:Position,busy:BusyPolicy,cost_fn:F) -> Option<(Vec<Position>,u32)>
+    where F : FnMut(&Bg) -> Option<u32> {
+        find_path(self,DenseNodes::new(self),from,to,busy,cost_fn)
+    }
+
+    /// Same as `HexMap::reachable`, with the bookkeeping of the search kept in vectors
+    /// indexed like the positions of the map.
+    pub fn reachable<F>(&self,from:Position,budget:u32,busy:BusyPolicy,cost_fn:F) -> Result<Reachable>
+    where F : FnMut(&Bg) -> Option<u32> {
+        reachable(self,DenseNodes::new(self),from,budget,busy,cost_fn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use map::*;
+    use map::tests::{Dummy,Bg,sample_map};
+    use pos::Position;
+
+    fn cost(bg:&Bg) -> Option<u32> {
+        match bg.kind.as_str() {
+            "Swamp" => Some(3),
+            "Chasm" => None,
+            _ => Some(1)
+        }
+    }
+
+    fn dummy() -> Dummy {
+        Dummy {pos:Position::default(),name:String::from("dummy")}
+    }
+
+    fn set_kind(map:&mut Map<Dummy,Bg>,position:Position,kind:&str) {
+        map.get_bg_mut(position).unwrap().kind = String::from(kind);
+    }
+
+    #[test]
+    fn straight_path(){
+        let map = sample_map();
+        let (path,cost) = map.find_path(Position::new(-2,0),Position::new(2,0),BusyPolicy::Blocked,cost).unwrap();
+        assert_eq!(cost,4);
+        assert_eq!(path,Position::new(-2,0).line_to(Position::new(2,0)));
+    }
+
+    #[test]
+    fn same_position(){
+        let map = sample_map();
+        assert_eq!(map.find_path(Position::new(1,1),Position::new(1,1),BusyPolicy::Blocked,cost),
+                   Some((vec![Position::new(1,1)],0)));
+    }
+
+    #[test]
+    fn out_of_range(){
+        let map = sample_map();
+        assert_eq!(map.find_path(Position::new(0,0),Position::new(20,0),BusyPolicy::Blocked,cost),None);
+    }
+
+    #[test]
+    fn avoid_costly_tiles(){
+        let mut map = sample_map();
+        for y in -3..4 {
+            set_kind(&mut map,Position::new(0,y),"Swamp");
+        }
+        set_kind(&mut map,Position::new(0,-4),"Chasm");
+        set_kind(&mut map,Position::new(0,4),"Obstacle");
+        let (path,cost) = map.find_path(Position::new(-2,0),Position::new(2,0),BusyPolicy::Blocked,cost).unwrap();
+        // crossing the swamp costs 3 + 3, going around it costs more
+        assert_eq!(cost,6);
+        assert_eq!(path.len(),5);
+        assert!(path.contains(&Position::new(0,0)) || path.contains(&Position::new(0,1))
+                || path.contains(&Position::new(0,-1)));
+        for pair in path.windows(2) {
+            assert_eq!(pair[0].distance_to(pair[1]),1);
+        }
+    }
+
+    #[test]
+    fn unreachable(){
+        let mut map = sample_map();
+        for position in Position::new(0,0).ring(1) {
+            set_kind(&mut map,position,"Obstacle");
+        }
+        assert_eq!(map.find_path(Position::new(0,0),Position::new(3,0),BusyPolicy::Passable,cost),None);
+        assert_eq!(map.find_path(Position::new(3,0),Position::new(0,0),BusyPolicy::Passable,cost),None);
+    }
+
+    #[test]
+    fn busy_policies(){
+        let mut map = sample_map();
+        for y in -5..5 {
+            if y != 2 {
+                map.create_content(Position::new(0,y),dummy()).unwrap();
+            }
+        }
+        set_kind(&mut map,Position::new(0,2),"Obstacle");
+        let (from,to) = (Position::new(-1,0),Position::new(1,0));
+        assert_eq!(map.find_path(from,to,BusyPolicy::Blocked,cost),None);
+        let (path,cost_passable) = map.find_path(from,to,BusyPolicy::Passable,cost).unwrap();
+        assert_eq!(cost_passable,2);
+        assert_eq!(path.len(),3);
+        let goal = Position::new(0,0);
+        assert_eq!(map.find_path(from,goal,BusyPolicy::PassableAtGoal,cost),
+                   Some((vec![from,goal],1)));
+        assert_eq!(map.find_path(from,to,BusyPolicy::PassableAtGoal,cost),None);
+    }
+
+    #[test]
+    fn deterministic(){
+        let map = sample_map();
+        let (from,to) = (Position::new(-3,-1),Position::new(3,2));
+        let first = map.find_path(from,to,BusyPolicy::Blocked,cost).unwrap();
+        for _ in 0..10 {
+            assert_eq!(map.find_path(from,to,BusyPolicy::Blocked,cost).unwrap(),first);
+        }
+        assert_eq!(first.1 as i32,from.distance_to(to));
+    }
+
+    #[test]
+    fn dense_and_generic_searches_agree(){
+        let mut map : Map<Dummy,Bg> = Map::with_wrap(MapShape::Parallelogram {width:8,height:6},
+                                                     Position::new(0,0),Wrap::Horizontal).unwrap();
+        for &(x,y) in &[(2,0),(2,1),(2,2),(2,3),(5,2),(5,3),(5,4),(5,5)] {
+            set_kind(&mut map,Position::new(x,y),"Swamp");
+        }
+        set_kind(&mut map,Position::new(3,4),"Obstacle");
+        map.create_content(Position::new(6,1),dummy()).unwrap();
+        let positions : Vec<Position> = map.iter_bg().map(|(position,_)| position).collect();
+        for &busy in &[BusyPolicy::Blocked,BusyPolicy::PassableAtGoal] {
+            for &to in &positions {
+                let from = Position::new(0,0);
+                let dense = map.find_path(from,to,busy,cost).map(|(_,cost)| cost);
+                let generic = super::find_path(&map,HashMap::new(),from,to,busy,cost).map(|(_,cost)| cost);
+                assert_eq!(dense,generic,"{:?}",to);
+            }
+            let dense = map.reachable(Position::new(8,0),4,busy,cost).unwrap();
+            let generic = super::reachable(&map,HashMap::new(),Position::new(8,0),4,busy,cost).unwrap();
+            assert_eq!(dense.origin(),generic.origin());
+            assert_eq!(dense.len(),generic.len());
+            for &position in generic.positions() {
+                assert_eq!(dense.cost(position),generic.cost(position),"{:?}",position);
+            }
+        }
+    }
+
+    #[test]
+    fn extreme_costs(){
+        let map = sample_map();
+        let (from,to) = (Position::new(-2,0),Position::new(2,0));
+        // a second step would cost more than `u32::MAX`
+        assert_eq!(map.find_path(from,to,BusyPolicy::Blocked,|_| Some(u32::MAX / 2 + 1)),None);
+        assert_eq!(map.find_path(from,Position::new(-1,0),BusyPolicy::Blocked,|_| Some(u32::MAX)).unwrap().1,u32::MAX);
+        // free steps count as 1, so that the path found is still the shortest
+        let (path,cost) = map.find_path(from,to,BusyPolicy::Blocked,|_| Some(0)).unwrap();
+        assert_eq!((path.len(),cost),(5,4));
+    }
+
+    #[test]
+    fn reachable_budget(){
+        let map = sample_map();
+        let origin = Position::new(0,0);
+        let reachable = map.reachable(origin,2,BusyPolicy::Blocked,cost).unwrap();
+        assert_eq!(reachable.len(),19);
+        assert_eq!(reachable.cost(origin),Some(0));
+        assert_eq!(reachable.cost(Position::new(2,-1)),Some(2));
+        assert_eq!(reachable.cost(Position::new(3,0)),None);
+        let costs : Vec<u32> = reachable.positions().map(|&p| reachable.cost(p).unwrap()).collect();
+        let mut sorted_costs = costs.clone();
+        sorted_costs.sort();
+        assert_eq!(costs,sorted_costs);
+    }
+
+    #[test]
+    fn reachable_extreme_costs(){
+        let map = sample_map();
+        let origin = Position::new(0,0);
+        let reachable = map.reachable(origin,u32::MAX,BusyPolicy::Blocked,|_| Some(u32::MAX / 2 + 1)).unwrap();
+        assert_eq!(reachable.len(),7);
+        assert_eq!(reachable.cost(Position::new(1,0)),Some(u32::MAX / 2 + 1));
+        let free = map.reachable(origin,2,BusyPolicy::Blocked,|_| Some(0)).unwrap();
+        assert_eq!(free.len(),19);
+        assert_eq!(free.cost(Position::new(2,0)),Some(2));
+    }
+
+    #[test]
+    fn reachable_paths(){
+        let mut map = sample_map();
+        set_kind(&mut map,Position::new(1,0),"Swamp");
+        set_kind(&mut map,Position::new(0,1),"Obstacle");
+        set_kind(&mut map,Position::new(1,-1),"Chasm");
+        let origin = Position::new(0,0);
+        let reachable = map.reachable(origin,3,BusyPolicy::Blocked,cost).unwrap();
+        assert!(!reachable.contains(Position::new(0,1)));
+        assert!(!reachable.contains(Position::new(1,-1)));
+        assert_eq!(reachable.cost(Position::new(1,0)),Some(3));
+        assert_eq!(reachable.path_to(Position::new(1,0)),Some(vec![origin,Position::new(1,0)]));
+        for &position in reachable.positions() {
+            let path = reachable.path_to(position).unwrap();
+            assert_eq!(path[0],origin);
+            assert_eq!(*path.last().unwrap(),position);
+            let path_cost : u32 = path[1..].iter().map(|&p| cost(map.get_bg(p).unwrap()).unwrap()).sum();
+            assert_eq!(Some(path_cost),reachable.cost(position));
+            assert_eq!(map.find_path(origin,position,BusyPolicy::Blocked,cost).unwrap().1,path_cost);
+        }
+        assert_eq!(reachable.path_to(Position::new(5,5)),None);
+    }
+
+    #[test]
+    fn reachable_busy(){
+        let mut map = sample_map();
+        map.create_content(Position::new(0,0),dummy()).unwrap();
+        map.create_content(Position::new(1,0),dummy()).unwrap();
+        let origin = Position::new(0,0);
+        let blocked = map.reachable(origin,2,BusyPolicy::Blocked,cost).unwrap();
+        assert!(!blocked.contains(Position::new(1,0)));
+        // (2,0) can only be reached in 2 steps through (1,0)
+        assert!(!blocked.contains(Position::new(2,0)));
+        assert_eq!(blocked.len(),17);
+        let at_goal = map.reachable(origin,2,BusyPolicy::PassableAtGoal,cost).unwrap();
+        assert_eq!(at_goal.cost(Position::new(1,0)),Some(1));
+        assert_eq!(at_goal.path_to(Position::new(1,0)),Some(vec![origin,Position::new(1,0)]));
+        assert!(!at_goal.contains(Position::new(2,0)));
+        let passable = map.reachable(origin,2,BusyPolicy::Passable,cost).unwrap();
+        assert_eq!(passable.len(),19);
+        assert_eq!(passable.predecessor(Position::new(2,0)),Some(Position::new(1,0)));
+        assert_eq!(map.reachable(Position::new(10,10),2,BusyPolicy::Blocked,cost).unwrap_err(),
+                   ::error::Error::new(::error::Reason::OutOfRange));
+    }
+}
@@ -0,0 +1,178 @@
+//! `Serialize` and `Deserialize` for `Map`, behind the `serde` feature.
+//!
+//! A map is stored as its shape, offset and wrap, every background in index order, and
+//! only the positions that hold some content. Loading rebuilds the map from the shape and
+//! calls `set_position` on every content, so contents may skip their own position.
+
+use serde::{Serialize,Serializer,Deserialize,Deserializer};
+use serde::de::Error as DeError;
+use error::{Error,Reason};
+use pos::Position;
+use map::{Map,MapShape,Wrap,PositionAccessor,AllowContent};
+
+#[derive(Serialize)]
+#[serde(rename="Map")]
+struct MapRef<'a,T:'a,Bg:'a> {
+    shape:MapShape,
+    offset:Position,
+    wrap:Wrap,
+    bgs:&'a [Bg],
+    contents:Vec<(Position,&'a T)>
+}
+
+#[derive(Deserialize)]
+#[serde(rename="Map")]
+struct MapData<T,Bg> {
+    shape:MapShape,
+    offset:Position,
+    wrap:Wrap,
+    bgs:Vec<Bg>,
+    contents:Vec<(Position,T)>
+}
+
+impl<T,Bg> Serialize for Map<T,Bg>
+    where T : PositionAccessor + Serialize, Bg : Default + AllowContent + Serialize {
+    fn serialize<S>(&self,serializer:S) -> Result<S::Ok,S::Error> where S : Serializer {
+        let contents = self.iter_contents()
+            .filter_map(|(position,content)| content.as_ref().map(|content| (position,content)))
+            .collect();
+        MapRef {
+            shape:self.shape,
+            offset:self.offset,
+            wrap:self.wrap,
+            bgs:&self.bg_slice,
+            contents
+        }.serialize(serializer)
+    }
+}
+
+impl<'de,T,Bg> Deserialize<'de> for Map<T,Bg>
+    where T : PositionAccessor + Deserialize<'de>, Bg : Default + AllowContent + Deserialize<'de> {
+    /// Contents are put back regardless of their background, like they were saved.
+    ///
+    /// # Errors
+    ///
+    /// * the errors of `Map::with_wrap` for the shape and wrap
+    /// * if the number of backgrounds does not match the shape
+    /// * `OutOfRange` if a content is outside of the map
+    /// * `AlreadyOccupied` if two contents share a position
+    fn deserialize<D>(deserializer:D) -> Result<Map<T,Bg>,D::Error> where D : Deserializer<'de> {
+        let data = MapData::<T,Bg>::deserialize(deserializer)?;
+        data.shape.validate().map_err(D::Error::custom)?;
+        // checked before allocating anything for the shape
+        if data.shape.checked_len() != Some(data.bgs.len()) {
+            return Err(D::Error::invalid_length(data.bgs.len(),&"one background per position of the shape"));
+        }
+        let mut map = Map::with_wrap(data.shape,data.offset,data.wrap).map_err(D::Error::custom)?;
+        map.bg_slice = data.bgs.into_boxed_slice();
+        for (position,mut content) in data.contents {
+            let index = map.pos_to_index(position).map_err(D::Error::custom)?;
+            let position = map.index_to_pos(index).map_err(D::Error::custom)?;
+            let slot = &mut map.contents_slice[index];
+            if slot.is_some() {
+                return Err(D::Error::custom(Error::new(Reason::AlreadyOccupied)));
+            }
+            content.set_position(position);
+            *slot = Some(content);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use pos::{Position,MainDirection,Direction,BaseVec};
+    use error::{Error,Reason};
+    use map::*;
+
+    #[derive(Debug,PartialEq,Serialize,Deserialize)]
+    struct Unit {
+        #[serde(skip)]
+        pos:Position,
+        hp:u32
+    }
+
+    impl PositionAccessor for Unit {
+        fn set_position(&mut self,new_position:Position) {
+            self.pos = new_position;
+        }
+        fn get_position(&self) -> Position {
+            self.pos
+        }
+    }
+
+    #[derive(Debug,Default,Clone,PartialEq,Serialize,Deserialize)]
+    struct Tile {
+        wall:bool
+    }
+
+    impl AllowContent for Tile {
+        fn is_content_allowed(&self) -> bool {
+            !self.wall
+        }
+    }
+
+    #[test]
+    fn positions_and_errors(){
+        let position = Position::new(3,-2);
+        let json = serde_json::to_string(&position).unwrap();
+        assert_eq!(serde_json::from_str::<Position>(&json).unwrap(),position);
+        let direction = Direction::MainDirection(MainDirection::SSW);
+        let json = serde_json::to_string(&direction).unwrap();
+        assert_eq!(serde_json::from_str::<Direction>(&json).unwrap(),direction);
+        let vec = BaseVec(MainDirection::E,4);
+        let json = serde_json::to_string(&vec).unwrap();
+        assert_eq!(serde_json::from_str::<BaseVec>(&json).unwrap().raw(),(MainDirection::E,4));
+        let error = Error::new(Reason::ForbiddenLocation);
+        let json = serde_json::to_string(&error).unwrap();
+        assert_eq!(serde_json::from_str::<Error>(&json).unwrap(),error);
+    }
+
+    #[test]
+    fn map_round_trip(){
+        let mut map : Map<Unit,Tile> = Map::with_wrap(MapShape::Parallelogram {width:4,height:3},Position::new(-1,-1),Wrap::Horizontal).unwrap();
+        map.create_content(Position::new(0,0),Unit {pos:Position::default(),hp:5}).unwrap();
+        map.create_content(Position::new(2,1),Unit {pos:Position::default(),hp:7}).unwrap();
+        map.get_bg_mut(Position::new(1,0)).unwrap().wall = true;
+        let json = serde_json::to_string(&map).unwrap();
+        let loaded : Map<Unit,Tile> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.shape(),map.shape());
+        assert_eq!(loaded.offset(),map.offset());
+        assert_eq!(loaded.wrap(),Wrap::Horizontal);
+        for ((position,content),(_,expected)) in loaded.iter_contents().zip(map.iter_contents()) {
+            assert_eq!(content,expected);
+            if let Some(ref content) = *content {
+                assert_eq!(content.pos,position);
+            }
+        }
+        assert!(loaded.iter_bg().zip(map.iter_bg()).all(|((_,a),(_,b))| a == b));
+        assert_eq!(loaded.get_contents(Position::new(2,1)).unwrap().as_ref().map(|u| u.hp),Some(7));
+    }
+
+    #[test]
+    fn invalid_map(){
+        let json = r#"{"shape":{"Hexagon":{"radius":1}},"offset":{"x":0,"y":0},"wrap":"None",
+            "bgs":[{"wall":false}],"contents":[]}"#;
+        assert!(serde_json::from_str::<Map<Unit,Tile>>(json).is_err());
+        let bgs = [r#"{"wall":false}"#;7].join(",");
+        let json = format!(r#"{{"shape":{{"Hexagon":{{"radius":1}}}},"offset":{{"x":0,"y":0}},"wrap":"None",
+            "bgs":[{}],"contents":[[{{"x":1,"y":0}},{{"hp":1}}],[{{"x":1,"y":0}},{{"hp":2}}]]}}"#,bgs);
+        assert!(serde_json::from_str::<Map<Unit,Tile>>(&json).is_err());
+        let json = json.replace(r#"[{"x":1,"y":0},{"hp":2}]"#,r#"[{"x":0,"y":-1},{"hp":2}]"#);
+        let loaded = serde_json::from_str::<Map<Unit,Tile>>(&json).unwrap();
+        assert_eq!(loaded.get_contents(Position::new(0,-1)).unwrap().as_ref().map(|u| u.pos),Some(Position::new(0,-1)));
+    }
+
+    #[test]
+    fn huge_shape(){
+        let json = r#"{"shape":{"Hexagon":{"radius":2147483647}},"offset":{"x":0,"y":0},"wrap":"None",
+            "bgs":[],"contents":[]}"#;
+        assert!(serde_json::from_str::<Map<Unit,Tile>>(json).is_err());
+        let json = r#"{"shape":{"Parallelogram":{"width":2147483647,"height":2147483647}},"offset":{"x":0,"y":0},
+            "wrap":"None","bgs":[{"wall":false}],"contents":[]}"#;
+        assert!(serde_json::from_str::<Map<Unit,Tile>>(json).is_err());
+        let json = r#"{"shape":{"Triangle":{"size":-1}},"offset":{"x":0,"y":0},"wrap":"None","bgs":[],"contents":[]}"#;
+        assert!(serde_json::from_str::<Map<Unit,Tile>>(json).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+use std::cmp::{max,min};
+use error::{Error,Reason,Result};
+use pos::{Position,OffsetPosition,OffsetKind};
+use layout::Orientation;
+
+/// How a map wraps around its edges.
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
+pub enum Wrap {
+    /// Positions outside of the shape do not exist
+    None,
+    /// Going out by the east edge leads to the west edge, and the other way around
+    Horizontal,
+    /// Same as `Horizontal`, and going out by the north edge leads to the south edge
+    Both
+}
+
+/// Set of positions a `Map` is made of, relatively to its offset.
+///
+/// Positions are stored row by row (by increasing `y`, or by increasing offset row for
+/// `Rectangle`), so that only the positions of the shape take some room.
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
+pub enum MapShape {
+    /// Positions with `0 <= x < width` and `0 <= y < height`
+    Parallelogram {
+        width:i32,
+        height:i32
+    },
+    /// Positions within `radius` of the offset, which is the center of the map
+    Hexagon {
+        radius:i32
+    },
+    /// `width` columns and `height` rows in offset coordinates (`OffsetKind::OddR` for
+    /// `Orientation::Pointy`, `OffsetKind::OddQ` for `Orientation::Flat`), the offset
+    /// being the top-left position
+    Rectangle {
+        orientation:Orientation,
+        width:i32,
+        height:i32
+    },
+    /// Positions with `x >= 0`, `y >= 0` and `x + y < size`
+    Triangle {
+        size:i32
+    }
+}
+
+/// Index of the first position of row `j` in a hexagon of radius `r`, for `j <= r`
+#[inline]
+fn hexagon_row_start(j:usize,r:usize) -> usize {
+    j * (r + 1) + j * j.saturating_sub(1) / 2
+}
+
+/// Index of the first position of row `y` in a triangle of size `size`
+#[inline]
+fn triangle_row_start(y:usize,size:usize) -> usize {
+    y * size - y * y.saturating_sub(1) / 2
+}
+
+impl MapShape {
+    /// # Errors
+    ///
+    /// * `NegativeMapLength` if one of the dimensions is not strictly positive (or
+    ///   negative for the radius of an hexagon)
+    pub fn validate(&self) -> Result<()> {
+        let valid = match *self {
+            MapShape::Parallelogram {width,height} => width > 0 && height > 0,
+            MapShape::Hexagon {radius} => radius >= 0,
+            MapShape::Rectangle {width,height,..} => width > 0 && height > 0,
+            MapShape::Triangle {size} => size > 0,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::new(Reason::NegativeMapLength))
+        }
+    }
+
+    /// Number of positions in the shape
+    pub fn len(&self) -> usize {
+        match *self {
+            MapShape::Parallelogram {width,height} | MapShape::Rectangle {width,height,..} => {
+                width as usize * height as usize
+            },
+            MapShape::Hexagon {radius} => {
+                let r = radius as usize ;
+                3 * r * (r + 1) + 1
+            },
+            MapShape::Triangle {size} => {
+                let n = size as usize ;
+                n * (n + 1) / 2
+            },
+        }
+    }
+
+    /// Same as `len`, or `None` if it does not fit in a `usize`
+    pub(crate) fn checked_len(&self) -> Option<usize> {
+        match *self {
+            MapShape::Parallelogram {width,height} | MapShape::Rectangle {width,height,..} => {
+                (width as usize).checked_mul(height as usize)
+            },
+            MapShape::Hexagon {radius} => {
+                let r = radius as usize ;
+                r.checked_mul(r + 1)?.checked_mul(3)?.checked_add(1)
+            },
+            MapShape::Triangle {size} => {
+                let n = size as usize ;
+                n.checked_mul(n + 1).map(|len| len / 2)
+            },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self,position:Position) -> bool {
+        self.pos_to_index(position).is_ok()
+    }
+
+    fn offset_kind(orientation:Orientation) -> OffsetKind {
+        match orientation {
+            Orientation::Pointy => OffsetKind::OddR,
+            Orientation::Flat => OffsetKind::OddQ,
+        }
+    }
+
+    /// Translations leading from a position to the same one when the shape wraps around,
+    /// east-west first and north-south second.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidTopology` if the shape cannot wrap this way: only parallelograms and
+    ///   rectangles can, and the wrapped dimension of a rectangle must be even when it is
+    ///   the staggered one (rows for `Pointy`, columns for `Flat`).
+    pub fn periods(&self,wrap:Wrap) -> Result<(Option<Position>,Option<Position>)> {
+        let invalid = Err(Error::new(Reason::InvalidTopology));
+        if wrap == Wrap::None {
+            return Ok((None,None));
+        }
+        let (horizontal,vertical) = match *self {
+            MapShape::Parallelogram {width,height} => (Position::new(width,0),Position::new(0,height)),
+            MapShape::Rectangle {orientation,width,height} => {
+                let kind = Self::offset_kind(orientation);
+                if (orientation == Orientation::Flat && width % 2 != 0)
+                || (orientation == Orientation::Pointy && wrap == Wrap::Both && height % 2 != 0) {
+                    return invalid;
+                }
+                (Position::from(OffsetPosition::new(width,0,kind)),
+                 Position::from(OffsetPosition::new(0,height,kind)))
+            },
+            _ => return invalid,
+        };
+        match wrap {
+            Wrap::Horizontal => Ok((Some(horizontal),None)),
+            _ => Ok((Some(horizontal),Some(vertical))),
+        }
+    }
+
+    /// Brings `position` (relative to the offset of the map) back into the shape along
+    /// the wrapped dimensions; `wrap` must be valid for this shape, see `periods`.
+    pub fn wrap(&self,position:Position,wrap:Wrap) -> Position {
+        if wrap == Wrap::None {
+            return position;
+        }
+        match *self {
+            MapShape::Parallelogram {width,height} => {
+                let y = if wrap == Wrap::Both { position.y.rem_euclid(height) } else { position.y };
+                Position::new(position.x.rem_euclid(width),y)
+            },
+            MapShape::Rectangle {orientation,width,height} => {
+                let mut offset = position.to_offset(Self::offset_kind(orientation));
+                offset.col = offset.col.rem_euclid(width);
+                if wrap == Wrap::Both {
+                    offset.row = offset.row.rem_euclid(height);
+                }
+                Position::from(offset)
+            },
+            _ => position,
+        }
+    }
+
+    /// Index of `position`, relative to the offset of the map
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not in the shape
+    pub fn pos_to_index(&self,position:Position) -> Result<usize> {
+        let (x,y) = (position.x,position.y);
+        let index = match *self {
+            MapShape::Parallelogram {width,height} => {
+                if x < 0 || x >= width || y < 0 || y >= height {
+                    None
+                } else {
+                    Some((x + width * y) as usize)
+                }
+            },
+            MapShape::Hexagon {radius} => {
+                let (lo,hi) = (max(-radius,-radius - y),min(radius,radius - y));
+                if y < -radius || y > radius || x < lo || x > hi {
+                    None
+                } else {
+                    let (j,r) = ((y + radius) as usize,radius as usize);
+                    let row_start = if j <= r {
+                        hexagon_row_start(j,r)
+                    } else {
+                        self.len() - hexagon_row_start(2 * r + 1 - j,r)
+                    };
+                    Some(row_start + (x - lo) as usize)
+                }
+            },
+            MapShape::Rectangle {orientation,width,height} => {
+                let offset = position.to_offset(Self::offset_kind(orientation));
+                if offset.col < 0 || offset.col >= width || offset.row < 0 || offset.row >= height {
+                    None
+                } else {
+                    Some((offset.col + width * offset.row) as usize)
+                }
+            },
+            MapShape::Triangle {size} => {
+                if x < 0 || y < 0 || x + y >= size {
+                    None
+                } else {
+                    Some(triangle_row_start(y as usize,size as usize) + x as usize)
+                }
+            },
+        };
+        index.ok_or(Error::new(Reason::OutOfRange))
+    }
+
+    /// Position at `index`, relative to the offset of the map
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if index is not lower than `len()`
+    pub fn index_to_pos(&self,index:usize) -> Result<Position> {
+        if index >= self.len() {
+            return Err(Error::new(Reason::OutOfRange));
+        }
+        let position = match *self {
+            MapShape::Parallelogram {width,..} => {
+                let width = width as usize ;
+                Position::new((index % width) as i32,(index / width) as i32)
+            },
+            MapShape::Hexagon {radius} => {
+                let r = radius as usize ;
+                let row_start = |j:usize| if j <= r {
+                    hexagon_row_start(j,r)
+                } else {
+                    self.len() - hexagon_row_start(2 * r + 1 - j,r)
+                };
+                // last row starting at or before index
+                let (mut lo,mut hi) = (0,2 * r);
+                while lo < hi {
+                    let mid = (lo + hi).div_ceil(2) ;
+                    if row_start(mid) <= index {
+                        lo = mid;
+                    } else {
+                        hi = mid - 1;
+                    }
+                }
+                let y = lo as i32 - radius ;
+                Position::new(max(-radius,-radius - y) + (index - row_start(lo)) as i32,y)
+            },
+            MapShape::Rectangle {orientation,width,..} => {
+                let width = width as usize ;
+                let offset = OffsetPosition::new((index % width) as i32,(index / width) as i32,
+                                                 Self::offset_kind(orientation));
+                Position::from(offset)
+            },
+            MapShape::Triangle {size} => {
+                let size = size as usize ;
+                let mut y = 0 ;
+                while triangle_row_start(y + 1,size) <= index {
+                    y += 1;
+                }
+                Position::new((index - triangle_row_start(y,size)) as i32,y as i32)
+            },
+        };
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pos::Position;
+    use layout::Orientation;
+
+    fn shapes() -> Vec<MapShape> {
+        vec![MapShape::Parallelogram {width:4,height:7},
+             MapShape::Hexagon {radius:0},
+             MapShape::Hexagon {radius:1},
+             MapShape::Hexagon {radius:5},
+             MapShape::Rectangle {orientation:Orientation::Pointy,width:5,height:4},
+             MapShape::Rectangle {orientation:Orientation::Flat,width:3,height:6},
+             MapShape::Triangle {size:1},
+             MapShape::Triangle {size:6}]
+    }
+
+    #[test]
+    fn round_trip(){
+        for shape in shapes() {
+            for index in 0..shape.len() {
+                let position = shape.index_to_pos(index).unwrap();
+                assert_eq!(shape.pos_to_index(position).unwrap(),index,"{:?}",shape);
+            }
+            assert!(shape.index_to_pos(shape.len()).is_err());
+            let inside = Position::new(0,0).iter_range(12).filter(|&p| shape.contains(p)).count();
+            assert_eq!(inside,shape.len(),"{:?}",shape);
+        }
+    }
+
+    #[test]
+    fn hexagon(){
+        let shape = MapShape::Hexagon {radius:3};
+        assert_eq!(shape.len(),37);
+        for position in Position::new(0,0).iter_range(5) {
+            assert_eq!(shape.contains(position),position.length() <= 3);
+        }
+        assert_eq!(shape.index_to_pos(0).unwrap(),Position::new(0,-3));
+        assert_eq!(shape.index_to_pos(36).unwrap(),Position::new(0,3));
+    }
+
+    #[test]
+    fn triangle(){
+        let shape = MapShape::Triangle {size:3};
+        assert_eq!(shape.len(),6);
+        assert!(shape.contains(Position::new(2,0)));
+        assert!(shape.contains(Position::new(0,2)));
+        assert!(!shape.contains(Position::new(1,2)));
+        assert!(!shape.contains(Position::new(-1,1)));
+    }
+
+    #[test]
+    fn validate(){
+        for shape in shapes() {
+            assert!(shape.validate().is_ok());
+        }
+        assert!(MapShape::Hexagon {radius:-1}.validate().is_err());
+        assert!(MapShape::Triangle {size:0}.validate().is_err());
+        assert!(MapShape::Rectangle {orientation:Orientation::Flat,width:0,height:3}.validate().is_err());
+    }
+
+    #[test]
+    fn wrap(){
+        let parallelogram = MapShape::Parallelogram {width:4,height:3};
+        assert_eq!(parallelogram.wrap(Position::new(-1,5),Wrap::None),Position::new(-1,5));
+        assert_eq!(parallelogram.wrap(Position::new(-1,5),Wrap::Horizontal),Position::new(3,5));
+        assert_eq!(parallelogram.wrap(Position::new(-1,5),Wrap::Both),Position::new(3,2));
+        let pointy = MapShape::Rectangle {orientation:Orientation::Pointy,width:5,height:4};
+        let flat = MapShape::Rectangle {orientation:Orientation::Flat,width:4,height:5};
+        for &shape in &[parallelogram,pointy,flat] {
+            let (horizontal,vertical) = shape.periods(Wrap::Both).unwrap();
+            let (horizontal,vertical) = (horizontal.unwrap(),vertical.unwrap());
+            for index in 0..shape.len() {
+                let position = shape.index_to_pos(index).unwrap();
+                for &(i,j) in &[(1,0),(-2,0),(0,1),(3,-1)] {
+                    let image = position + horizontal * i + vertical * j;
+                    assert!(!shape.contains(image));
+                    assert_eq!(shape.wrap(image,Wrap::Both),position,"{:?}",shape);
+                }
+            }
+        }
+        assert!(MapShape::Hexagon {radius:3}.periods(Wrap::Horizontal).is_err());
+        assert!(MapShape::Hexagon {radius:3}.periods(Wrap::None).is_ok());
+        assert!(MapShape::Rectangle {orientation:Orientation::Pointy,width:5,height:3}.periods(Wrap::Horizontal).is_ok());
+        assert!(MapShape::Rectangle {orientation:Orientation::Pointy,width:5,height:3}.periods(Wrap::Both).is_err());
+        assert!(MapShape::Rectangle {orientation:Orientation::Flat,width:5,height:4}.periods(Wrap::Horizontal).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::collections::hash_map;
+use error::{Error,Reason,Result};
+use pos::Position;
+use map::{MapShape,HexMap,HexMapIter,PositionAccessor,AllowContent,PositionStatus};
+
+/// A map storing only the positions which hold a content or a `Bg` different from the
+/// default one, for big maps with few contents.
+///
+/// It follows the same rules as `Map`: positions outside of its shape are `OutOfRange`,
+/// contents cannot be put where the `Bg` does not allow them, and `set_position` is
+/// called every time a content moves.
+pub struct SparseMap<T : PositionAccessor,Bg : AllowContent> {
+    contents : HashMap<Position,T>,
+    bgs : HashMap<Position,Bg>,
+    default_bg : Bg,
+    shape : MapShape,
+    offset : Position
+}
+
+impl<T,Bg> SparseMap<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    /// Creates a map shaped as a parallelogram, see `MapShape::Parallelogram`.
+    pub fn new(length:(i32,i32),offset:Position) -> Result<SparseMap<T,Bg>> {
+        Self::with_shape(MapShape::Parallelogram {width:length.0,height:length.1},offset)
+    }
+
+    pub fn with_shape(shape:MapShape,offset:Position) -> Result<SparseMap<T,Bg>> {
+        Self::with_default_bg(shape,offset,Bg::default())
+    }
+}
+
+impl<T,Bg> SparseMap<T,Bg> where T : PositionAccessor, Bg : AllowContent {
+    /// Creates a map where every position has `default_bg` until told otherwise.
+    pub fn with_default_bg(shape:MapShape,offset:Position,default_bg:Bg) -> Result<SparseMap<T,Bg>> {
+        shape.validate()?;
+        Ok(SparseMap {
+            contents:HashMap::new(),
+            bgs:HashMap::new(),
+            default_bg,
+            shape,
+            offset
+        })
+    }
+
+    pub fn shape(&self) -> MapShape {
+        self.shape
+    }
+
+    pub fn offset(&self) -> Position {
+        self.offset
+    }
+
+    pub fn default_bg(&self) -> &Bg {
+        &self.default_bg
+    }
+
+    /// Whether `position` is part of the map
+    pub fn contains(&self,position:Position) -> bool {
+        self.shape.contains(position - self.offset)
+    }
+
+    fn check(&self,position:Position) -> Result<()> {
+        if self.contains(position) {
+            Ok(())
+        } else {
+            Err(Error::new(Reason::OutOfRange))
+        }
+    }
+
+    /// Number of positions holding a content
+    pub fn contents_count(&self) -> usize {
+        self.contents.len()
+    }
+
+    pub fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        let (content,bg) = self.get(position)?;
+        let result = match content {
+            Some(_) => PositionStatus::Busy,
+            None if bg.is_content_allowed() => PositionStatus::Empty,
+            None => PositionStatus::Forbidden,
+        };
+        Ok(result)
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get(&self,position:Position) -> Result<(Option<&T>,&Bg)> {
+        Ok((self.get_contents(position)?,self.get_bg(position)?))
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_contents(&self,position:Position) -> Result<Option<&T>> {
+        self.check(position)?;
+        Ok(self.contents.get(&position))
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_contents_mut(&mut self,position:Position) -> Result<Option<&mut T>> {
+        self.check(position)?;
+        Ok(self.contents.get_mut(&position))
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg(&self,position:Position) -> Result<&Bg> {
+        self.check(position)?;
+        Ok(self.bgs.get(&position).unwrap_or(&self.default_bg))
+    }
+
+    /// Mutable `Bg` of `position`, which starts as a copy of the default one if it was
+    /// not set yet.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg_mut(&mut self,position:Position) -> Result<&mut Bg> where Bg : Clone {
+        self.check(position)?;
+        let default_bg = &self.default_bg;
+        Ok(self.bgs.entry(position).or_insert_with(|| default_bg.clone()))
+    }
+
+    /// Sets the `Bg` of `position` and returns the previous one, if it was not the
+    /// default one.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn set_bg(&mut self,position:Position,bg:Bg) -> Result<Option<Bg>> {
+        self.check(position)?;
+        Ok(self.bgs.insert(position,bg))
+    }
+
+    /// Puts back the default `Bg` at `position`, and returns the previous one if it was
+    /// not the default one.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn reset_bg(&mut self,position:Position) -> Result<Option<Bg>> {
+        self.check(position)?;
+        Ok(self.bgs.remove(&position))
+    }
+
+    /// Replace a `Position` with a new content.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `MissingTarget` if Position has no content (`None`)
+    pub fn replace_content(&mut self,position:Position,mut new_content:T) -> Result<T> {
+        self.check(position)?;
+        match self.contents.get_mut(&position) {
+            Some(content) => {
+                new_content.set_position(position);
+                Ok(::std::mem::replace(content,new_content))
+            },
+            None => Err(Error::new(Reason::MissingTarget)),
+        }
+    }
+
+    /// Extract a content at `Position`.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `MissingTarget` if Position has no content (`None`)
+    pub fn extract_content(&mut self,position:Position) -> Result<T> {
+        self.check(position)?;
+        self.contents.remove(&position).ok_or(Error::new(Reason::MissingTarget))
+    }
+
+    /// Put a new content at `Position`.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `AlreadyOccupied` if Position is busy
+    /// * `ForbiddenLocation` if the `Bg` of Position does not allow contents
+    pub fn create_content(&mut self,position:Position,mut new_content:T) -> Result<()> {
+        match self.position_status(position)? {
+            PositionStatus::Empty => (),
+            PositionStatus::Busy => return Err(Error::new(Reason::AlreadyOccupied)),
+            PositionStatus::Forbidden => return Err(Error::new(Reason::ForbiddenLocation)),
+        }
+        new_content.set_position(position);
+        self.contents.insert(position,new_content);
+        Ok(())
+    }
+
+    /// Swap 2 elements.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if one or more positions is not valid
+    /// * `MissingTarget` if one the 2 position has no content (`None`)
+    pub fn swap_contents(&mut self,pos_1:Position,pos_2:Position) -> Result<()> {
+        self.check(pos_1)?;
+        self.check(pos_2)?;
+        if !self.contents.contains_key(&pos_1) || !self.contents.contains_key(&pos_2) {
+            return Err(Error::new(Reason::MissingTarget));
+        }
+        if pos_1 == pos_2 {
+            return Ok(());
+        }
+        let mut content_1 = self.contents.remove(&pos_1).unwrap();
+        content_1.set_position(pos_2);
+        let mut content_2 = self.contents.insert(pos_2,content_1).unwrap();
+        content_2.set_position(pos_1);
+        self.contents.insert(pos_1,content_2);
+        Ok(())
+    }
+
+    /// Move an element from a position to another
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if one or more positions are not valid
+    /// * `MissingTarget` if the initial position has no element
+    /// * `AlreadyOccupied` if the final position is busy
+    /// * `ForbiddenLocation` if the `Bg` of the final position does not allow contents
+    pub fn move_contents(&mut self,from:Position,to:Position) -> Result<()> {
+        self.check(from)?;
+        match self.position_status(to)? {
+            PositionStatus::Empty => (),
+            PositionStatus::Busy => return Err(Error::new(Reason::AlreadyOccupied)),
+            PositionStatus::Forbidden => return Err(Error::new(Reason::ForbiddenLocation)),
+        }
+        let mut content = self.contents.remove(&from).ok_or(Error::new(Reason::MissingTarget))?;
+        content.set_position(to);
+        self.contents.insert(to,content);
+        Ok(())
+    }
+
+    /// Every content with its position, in no particular order
+    pub fn iter_contents(&self) -> hash_map::Iter<'_,Position,T> {
+        self.contents.iter()
+    }
+
+    pub fn iter_contents_mut(&mut self) -> hash_map::IterMut<'_,Position,T> {
+        self.contents.iter_mut()
+    }
+
+    /// Every `Bg` which is not the default one with its position, in no particular order
+    pub fn iter_bg(&self) -> hash_map::Iter<'_,Position,Bg> {
+        self.bgs.iter()
+    }
+}
+
+impl<T,Bg> HexMap for SparseMap<T,Bg> where T : PositionAccessor, Bg : Clone + AllowContent {
+    type Content = T ;
+    type Bg = Bg ;
+
+    fn get(&self,position:Position) -> Result<(Option<&T>,&Bg)> {
+        SparseMap::get(self,position)
+    }
+
+    /// The `Bg` of `position` starts as a copy of the default one if it was not set yet.
+    fn get_mut(&mut self,position:Position) -> Result<(Option<&mut T>,&mut Bg)> {
+        self.check(position)?;
+        let default_bg = &self.default_bg;
+        let bg = self.bgs.entry(position).or_insert_with(|| default_bg.clone());
+        Ok((self.contents.get_mut(&position),bg))
+    }
+
+    fn contains(&self,position:Position) -> bool {
+        SparseMap::contains(self,position)
+    }
+
+    /// Positions holding a content or a `Bg` which is not the default one.
+    fn iter<'a>(&'a self) -> HexMapIter<'a,T,Bg> {
+        let with_content = self.contents.iter().map(move |(&position,content)| {
+            (position,Some(content),self.bgs.get(&position).unwrap_or(&self.default_bg))
+        });
+        let without_content = self.bgs.iter()
+            .filter(move |&(position,_)| !self.contents.contains_key(position))
+            .map(|(&position,bg)| (position,None,bg));
+        Box::new(with_content.chain(without_content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::BusyPolicy;
+    use map::tests::{Dummy,Bg};
+    use pos::Position;
+
+    fn dummy(name:&str) -> Dummy {
+        Dummy {pos:Position::default(),name:String::from(name)}
+    }
+
+    fn sample_map() -> SparseMap<Dummy,Bg> {
+        SparseMap::with_shape(MapShape::Hexagon {radius:1000},Position::new(0,0)).unwrap()
+    }
+
+    #[test]
+    fn routine_test(){
+        let mut map = sample_map();
+        map.create_content(Position::new(0,0),dummy("a")).unwrap();
+        map.create_content(Position::new(500,-20),dummy("b")).unwrap();
+        assert_eq!(map.contents_count(),2);
+        assert_eq!(map.create_content(Position::new(0,0),dummy("c")).unwrap_err(),
+                   Error::new(Reason::AlreadyOccupied));
+        assert_eq!(map.create_content(Position::new(1001,0),dummy("c")).unwrap_err(),
+                   Error::new(Reason::OutOfRange));
+        map.get_bg_mut(Position::new(4,0)).unwrap().kind = String::from("Obstacle");
+        assert_eq!(map.create_content(Position::new(4,0),dummy("c")).unwrap_err(),
+                   Error::new(Reason::ForbiddenLocation));
+        assert_eq!(map.move_contents(Position::new(0,0),Position::new(4,0)).unwrap_err(),
+                   Error::new(Reason::ForbiddenLocation));
+        map.move_contents(Position::new(0,0),Position::new(3,0)).unwrap();
+        assert_eq!(map.get_contents(Position::new(3,0)).unwrap().unwrap().get_position(),
+                   Position::new(3,0));
+        map.swap_contents(Position::new(3,0),Position::new(500,-20)).unwrap();
+        assert_eq!(map.get_contents(Position::new(3,0)).unwrap().unwrap().name,"b");
+        assert_eq!(map.get_contents(Position::new(3,0)).unwrap().unwrap().get_position(),
+                   Position::new(3,0));
+        assert_eq!(map.swap_contents(Position::new(3,0),Position::new(1,1)).unwrap_err(),
+                   Error::new(Reason::MissingTarget));
+        map.swap_contents(Position::new(3,0),Position::new(3,0)).unwrap();
+        assert_eq!(map.get_contents(Position::new(3,0)).unwrap().unwrap().name,"b");
+        assert_eq!(map.swap_contents(Position::new(1,1),Position::new(1,1)).unwrap_err(),
+                   Error::new(Reason::MissingTarget));
+        let replaced = map.replace_content(Position::new(3,0),dummy("d")).unwrap();
+        assert_eq!(replaced.name,"b");
+        assert_eq!(map.extract_content(Position::new(500,-20)).unwrap().name,"a");
+        assert_eq!(map.extract_content(Position::new(500,-20)).unwrap_err(),
+                   Error::new(Reason::MissingTarget));
+        assert_eq!(map.iter_contents().count(),1);
+    }
+
+    #[test]
+    fn default_bg(){
+        let obstacle = Bg {kind:String::from("Obstacle")};
+        let mut map : SparseMap<Dummy,Bg> =
+            SparseMap::with_default_bg(MapShape::Triangle {size:10},Position::new(0,0),obstacle).unwrap();
+        assert_eq!(map.get_bg(Position::new(2,2)).unwrap().kind,"Obstacle");
+        assert_eq!(map.create_content(Position::new(2,2),dummy("a")).unwrap_err(),
+                   Error::new(Reason::ForbiddenLocation));
+        map.set_bg(Position::new(2,2),Bg::default()).unwrap();
+        map.create_content(Position::new(2,2),dummy("a")).unwrap();
+        assert_eq!(map.iter_bg().count(),1);
+        assert_eq!(map.reset_bg(Position::new(2,2)).unwrap().unwrap().kind,"");
+        assert_eq!(map.iter_bg().count(),0);
+        assert_eq!(map.get_bg(Position::new(20,2)).unwrap_err(),Error::new(Reason::OutOfRange));
+    }
+
+    #[test]
+    fn hex_map(){
+        let mut map = sample_map();
+        map.set_bg(Position::new(1,0),Bg {kind:String::from("Obstacle")}).unwrap();
+        map.create_content(Position::new(2,0),dummy("a")).unwrap();
+        assert_eq!(HexMap::iter(&map).count(),2);
+        let (path,cost) = map.find_path(Position::new(0,0),Position::new(2,0),BusyPolicy::PassableAtGoal,|_| Some(1)).unwrap();
+        assert_eq!(cost,3);
+        assert!(!path.contains(&Position::new(1,0)));
+        {
+            let (content,bg) = HexMap::get_mut(&mut map,Position::new(2,0)).unwrap();
+            content.unwrap().name = String::from("b");
+            bg.kind = String::from("Swamp");
+        }
+        assert_eq!(map.get_contents(Position::new(2,0)).unwrap().unwrap().name,"b");
+        assert_eq!(map.get_bg(Position::new(2,0)).unwrap().kind,"Swamp");
+    }
+}
@@ -0,0 +1,423 @@
+use std::slice::{Iter,IterMut};
+use std::iter::Zip;
+use error::{Error,Reason,Result};
+use pos::Position;
+use map::{MapShape,MapIter,HexMap,HexMapIter,PositionAccessor,AllowContent,PositionStatus};
+
+/// Function of `Capacity::Custom`
+pub type CapacityFn<T,Bg> = Box<dyn Fn(&Bg,&[T]) -> bool + Send + Sync>;
+
+/// How many contents a position of a `StackMap` can hold.
+pub enum Capacity<T,Bg> {
+    /// Any number of contents
+    Unbounded,
+    /// At most this number of contents
+    Fixed(usize),
+    /// Whether one more content can be put on a stack, given the `Bg` of its position and
+    /// the contents already there
+    Custom(CapacityFn<T,Bg>)
+}
+
+impl<T,Bg> Capacity<T,Bg> {
+    /// `Capacity::Custom` from a closure, which may capture some settings of the game
+    pub fn custom<F>(allows:F) -> Capacity<T,Bg> where F : Fn(&Bg,&[T]) -> bool + Send + Sync + 'static {
+        Capacity::Custom(Box::new(allows))
+    }
+
+    /// Whether one more content can be put on `stack`
+    pub fn allows(&self,bg:&Bg,stack:&[T]) -> bool {
+        match *self {
+            Capacity::Unbounded => true,
+            Capacity::Fixed(capacity) => stack.len() < capacity,
+            Capacity::Custom(ref allows) => allows(bg,stack),
+        }
+    }
+}
+
+/// A map where every position holds an ordered stack of contents instead of at most one.
+///
+/// Index 0 is the bottom of a stack. It follows the same rules as `Map` otherwise: contents
+/// cannot be put where the `Bg` does not allow them, nor on a stack which is full according
+/// to the `Capacity` of the map, and `set_position` is called every time a content changes
+/// of position.
+pub struct StackMap<T : PositionAccessor,Bg : Default + AllowContent> {
+    stacks : Box<[Vec<T>]>,
+    bg_slice : Box<[Bg]>,
+    shape : MapShape,
+    offset : Position,
+    capacity : Capacity<T,Bg>
+}
+
+impl<T,Bg> StackMap<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    /// Creates a map shaped as a parallelogram, see `MapShape::Parallelogram`.
+    pub fn new(length:(i32,i32),offset:Position,capacity:Capacity<T,Bg>) -> Result<StackMap<T,Bg>> {
+        Self::with_shape(MapShape::Parallelogram {width:length.0,height:length.1},offset,capacity)
+    }
+
+    pub fn with_shape(shape:MapShape,offset:Position,capacity:Capacity<T,Bg>) -> Result<StackMap<T,Bg>> {
+        shape.validate()?;
+        let total_len = shape.len();
+        let mut stacks = Vec::with_capacity(total_len);
+        let mut bgs = Vec::with_capacity(total_len);
+        for _i in 0 .. total_len {
+            stacks.push(Vec::new());
+            bgs.push(Bg::default());
+        }
+        Ok(StackMap {
+            stacks:stacks.into_boxed_slice(),
+            bg_slice:bgs.into_boxed_slice(),
+            shape,
+            offset,
+            capacity
+        })
+    }
+
+    pub fn shape(&self) -> MapShape {
+        self.shape
+    }
+
+    pub fn offset(&self) -> Position {
+        self.offset
+    }
+
+    pub fn capacity(&self) -> &Capacity<T,Bg> {
+        &self.capacity
+    }
+
+    /// Whether `position` is part of the map
+    pub fn contains(&self,position:Position) -> bool {
+        self.pos_to_index(position).is_ok()
+    }
+
+    fn pos_to_index(&self,position:Position) -> Result<usize> {
+        self.shape.pos_to_index(position - self.offset)
+    }
+
+    /// Number of contents on every stack
+    pub fn contents_count(&self) -> usize {
+        self.stacks.iter().map(Vec::len).sum()
+    }
+
+    /// `Empty` if one more content can be put at `position`, `Busy` if its stack is full
+    /// and `Forbidden` if its `Bg` does not allow contents.
+    pub fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        let index = self.pos_to_index(position)?;
+        self.status_at(index)
+    }
+
+    fn status_at(&self,index:usize) -> Result<PositionStatus> {
+        let (stack,bg) = (&self.stacks[index],&self.bg_slice[index]);
+        if !bg.is_content_allowed() {
+            Ok(PositionStatus::Forbidden)
+        } else if self.capacity.allows(bg,stack) {
+            Ok(PositionStatus::Empty)
+        } else {
+            Ok(PositionStatus::Busy)
+        }
+    }
+
+    fn check_room(&self,index:usize) -> Result<()> {
+        match self.status_at(index)? {
+            PositionStatus::Empty => Ok(()),
+            PositionStatus::Busy => Err(Error::new(Reason::AlreadyOccupied)),
+            PositionStatus::Forbidden => Err(Error::new(Reason::ForbiddenLocation)),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get(&self,position:Position) -> Result<(&[T],&Bg)> {
+        let index = self.pos_to_index(position)?;
+        Ok((&self.stacks[index],&self.bg_slice[index]))
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_mut(&mut self,position:Position) -> Result<(&mut [T],&mut Bg)> {
+        let index = self.pos_to_index(position)?;
+        Ok((&mut self.stacks[index],&mut self.bg_slice[index]))
+    }
+
+    /// Contents of `position`, from the bottom of the stack to its top.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_stack(&self,position:Position) -> Result<&[T]> {
+        let index = self.pos_to_index(position)?;
+        Ok(&self.stacks[index])
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_stack_mut(&mut self,position:Position) -> Result<&mut [T]> {
+        let index = self.pos_to_index(position)?;
+        Ok(&mut self.stacks[index])
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg(&self,position:Position) -> Result<&Bg> {
+        let index = self.pos_to_index(position)?;
+        Ok(&self.bg_slice[index])
+    }
+
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    pub fn get_bg_mut(&mut self,position:Position) -> Result<&mut Bg> {
+        let index = self.pos_to_index(position)?;
+        Ok(&mut self.bg_slice[index])
+    }
+
+    /// Put a new content on top of the stack of `position`.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `AlreadyOccupied` if the stack is full
+    /// * `ForbiddenLocation` if the `Bg` of position does not allow contents
+    pub fn push_content(&mut self,position:Position,new_content:T) -> Result<()> {
+        let index = self.pos_to_index(position)?;
+        let stack_index = self.stacks[index].len();
+        self.insert_at(index,position,stack_index,new_content)
+    }
+
+    /// Put a new content at `stack_index` in the stack of `position`, moving up the ones
+    /// above it.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid, or if `stack_index` is greater than the
+    ///   size of the stack
+    /// * `AlreadyOccupied` if the stack is full
+    /// * `ForbiddenLocation` if the `Bg` of position does not allow contents
+    pub fn insert_content(&mut self,position:Position,stack_index:usize,new_content:T) -> Result<()> {
+        let index = self.pos_to_index(position)?;
+        self.insert_at(index,position,stack_index,new_content)
+    }
+
+    fn insert_at(&mut self,index:usize,position:Position,stack_index:usize,mut new_content:T) -> Result<()> {
+        if stack_index > self.stacks[index].len() {
+            return Err(Error::new(Reason::OutOfRange));
+        }
+        self.check_room(index)?;
+        new_content.set_position(position);
+        self.stacks[index].insert(stack_index,new_content);
+        Ok(())
+    }
+
+    /// Extract the content on top of the stack of `position`.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `MissingTarget` if the stack is empty
+    pub fn pop_content(&mut self,position:Position) -> Result<T> {
+        let index = self.pos_to_index(position)?;
+        self.stacks[index].pop().ok_or(Error::new(Reason::MissingTarget))
+    }
+
+    /// Extract the content at `stack_index` in the stack of `position`, moving down the
+    /// ones above it.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if position is not valid
+    /// * `MissingTarget` if the stack has no content at `stack_index`
+    pub fn remove_content(&mut self,position:Position,stack_index:usize) -> Result<T> {
+        let index = self.pos_to_index(position)?;
+        if stack_index < self.stacks[index].len() {
+            Ok(self.stacks[index].remove(stack_index))
+        } else {
+            Err(Error::new(Reason::MissingTarget))
+        }
+    }
+
+    /// Move the content at `stack_index` in the stack of `from` on top of the stack of `to`.
+    ///
+    /// # Errors
+    ///
+    /// See `move_content_at`
+    pub fn move_content(&mut self,from:Position,stack_index:usize,to:Position) -> Result<()> {
+        let to_index = self.pos_to_index(to)?;
+        let to_stack_index = if from == to {
+            self.stacks[to_index].len().saturating_sub(1)
+        } else {
+            self.stacks[to_index].len()
+        };
+        self.move_content_at(from,stack_index,to,to_stack_index)
+    }
+
+    /// Move the content at `from_stack_index` in the stack of `from` so that it ends up at
+    /// `to_stack_index` in the stack of `to`. When `from` and `to` are the same position,
+    /// the stack is only reordered and its capacity is not checked.
+    ///
+    /// # Errors
+    ///
+    /// * `OutOfRange` if one or more positions are not valid, or if `to_stack_index` is
+    ///   greater than the size of the final stack
+    /// * `MissingTarget` if the stack of `from` has no content at `from_stack_index`
+    /// * `AlreadyOccupied` if the stack of `to` is full
+    /// * `ForbiddenLocation` if the `Bg` of `to` does not allow contents
+    pub fn move_content_at(&mut self,from:Position,from_stack_index:usize,to:Position,to_stack_index:usize) -> Result<()> {
+        let index_from = self.pos_to_index(from)?;
+        let index_to = self.pos_to_index(to)?;
+        if from_stack_index >= self.stacks[index_from].len() {
+            return Err(Error::new(Reason::MissingTarget));
+        }
+        if index_from == index_to {
+            if to_stack_index >= self.stacks[index_to].len() {
+                return Err(Error::new(Reason::OutOfRange));
+            }
+            let content = self.stacks[index_from].remove(from_stack_index);
+            self.stacks[index_to].insert(to_stack_index,content);
+            return Ok(());
+        }
+        if to_stack_index > self.stacks[index_to].len() {
+            return Err(Error::new(Reason::OutOfRange));
+        }
+        self.check_room(index_to)?;
+        let mut content = self.stacks[index_from].remove(from_stack_index);
+        content.set_position(to);
+        self.stacks[index_to].insert(to_stack_index,content);
+        Ok(())
+    }
+
+    pub fn iter_stacks(&self) -> MapIter<Iter<'_,Vec<T>>> {
+        MapIter::new(self.stacks.iter(),self.shape,self.offset)
+    }
+
+    pub fn iter_stacks_mut(&mut self) -> MapIter<IterMut<'_,Vec<T>>> {
+        MapIter::new(self.stacks.iter_mut(),self.shape,self.offset)
+    }
+
+    /// Every content with its position, from the bottom of each stack to its top
+    pub fn iter_contents(&self) -> impl Iterator<Item=(Position,&T)> {
+        self.iter_stacks().flat_map(|(position,stack)| stack.iter().map(move |content| (position,content)))
+    }
+
+    pub fn iter_bg(&self) -> MapIter<Iter<'_,Bg>> {
+        MapIter::new(self.bg_slice.iter(),self.shape,self.offset)
+    }
+
+    pub fn iter_bg_mut(&mut self) -> MapIter<IterMut<'_,Bg>> {
+        MapIter::new(self.bg_slice.iter_mut(),self.shape,self.offset)
+    }
+
+    pub fn iter(&self) -> MapIter<Zip<Iter<'_,Vec<T>>,Iter<'_,Bg>>> {
+        MapIter::new(self.stacks.iter().zip(self.bg_slice.iter()),self.shape,self.offset)
+    }
+}
+
+/// The content of a position is the top of its stack, and its status is the one given by
+/// `StackMap::position_status`.
+impl<T,Bg> HexMap for StackMap<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    type Content = T ;
+    type Bg = Bg ;
+
+    fn get(&self,position:Position) -> Result<(Option<&T>,&Bg)> {
+        StackMap::get(self,position).map(|(stack,bg)| (stack.last(),bg))
+    }
+
+    fn get_mut(&mut self,position:Position) -> Result<(Option<&mut T>,&mut Bg)> {
+        StackMap::get_mut(self,position).map(|(stack,bg)| (stack.last_mut(),bg))
+    }
+
+    fn contains(&self,position:Position) -> bool {
+        StackMap::contains(self,position)
+    }
+
+    fn iter<'a>(&'a self) -> HexMapIter<'a,T,Bg> {
+        Box::new(StackMap::iter(self).map(|(position,(stack,bg))| (position,stack.last(),bg)))
+    }
+
+    fn position_status(&self,position:Position) -> Result<PositionStatus> {
+        StackMap::position_status(self,position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::BusyPolicy;
+    use map::tests::{Dummy,Bg};
+
+    fn dummy(name:&str) -> Dummy {
+        Dummy {pos:Position::default(),name:String::from(name)}
+    }
+
+    fn names(map:&StackMap<Dummy,Bg>,position:Position) -> Vec<&str> {
+        map.get_stack(position).unwrap().iter().map(|dummy| dummy.name.as_str()).collect()
+    }
+
+    #[test]
+    fn routine_test(){
+        let mut map : StackMap<Dummy,Bg> = StackMap::with_shape(MapShape::Hexagon {radius:3},Position::default(),Capacity::Fixed(3)).unwrap();
+        let (a,b) = (Position::new(0,0),Position::new(1,-1));
+        map.push_content(a,dummy("a")).unwrap();
+        map.push_content(a,dummy("c")).unwrap();
+        map.insert_content(a,1,dummy("b")).unwrap();
+        assert_eq!(names(&map,a),vec!["a","b","c"]);
+        assert_eq!(map.push_content(a,dummy("d")).unwrap_err(),Error::new(Reason::AlreadyOccupied));
+        assert_eq!(map.insert_content(b,1,dummy("d")).unwrap_err(),Error::new(Reason::OutOfRange));
+        assert_eq!(map.push_content(Position::new(4,0),dummy("d")).unwrap_err(),Error::new(Reason::OutOfRange));
+        map.get_bg_mut(b).unwrap().kind = String::from("Obstacle");
+        assert_eq!(map.push_content(b,dummy("d")).unwrap_err(),Error::new(Reason::ForbiddenLocation));
+        assert_eq!(map.move_content(a,0,b).unwrap_err(),Error::new(Reason::ForbiddenLocation));
+        map.get_bg_mut(b).unwrap().kind = String::new();
+
+        map.move_content(a,1,b).unwrap();
+        map.move_content_at(a,0,b,0).unwrap();
+        assert_eq!(names(&map,a),vec!["c"]);
+        assert_eq!(names(&map,b),vec!["a","b"]);
+        assert!(map.get_stack(b).unwrap().iter().all(|dummy| dummy.get_position() == b));
+        assert_eq!(map.move_content(a,1,b).unwrap_err(),Error::new(Reason::MissingTarget));
+        map.move_content(b,0,b).unwrap();
+        assert_eq!(names(&map,b),vec!["b","a"]);
+        assert_eq!(map.move_content_at(b,0,b,2).unwrap_err(),Error::new(Reason::OutOfRange));
+
+        assert_eq!(map.remove_content(b,0).unwrap().name,"b");
+        assert_eq!(map.remove_content(b,1).unwrap_err(),Error::new(Reason::MissingTarget));
+        assert_eq!(map.pop_content(b).unwrap().name,"a");
+        assert_eq!(map.pop_content(b).unwrap_err(),Error::new(Reason::MissingTarget));
+        assert_eq!(map.contents_count(),1);
+        assert_eq!(map.iter_contents().map(|(position,dummy)| (position,dummy.name.as_str())).collect::<Vec<_>>(),
+                   vec![(a,"c")]);
+    }
+
+    #[test]
+    fn custom_capacity(){
+        // a swamp holds a single content, anything else holds as many as wanted
+        let swamp_capacity = 1;
+        let capacity = Capacity::custom(move |bg:&Bg,stack:&[Dummy]| bg.kind != "Swamp" || stack.len() < swamp_capacity);
+        let mut map : StackMap<Dummy,Bg> = StackMap::new((3,3),Position::default(),capacity).unwrap();
+        let (swamp,plain) = (Position::new(1,1),Position::new(2,1));
+        map.get_bg_mut(swamp).unwrap().kind = String::from("Swamp");
+        for i in 0..10 {
+            map.push_content(plain,dummy(&i.to_string())).unwrap();
+        }
+        map.move_content(plain,9,swamp).unwrap();
+        assert_eq!(map.move_content(plain,0,swamp).unwrap_err(),Error::new(Reason::AlreadyOccupied));
+        assert_eq!(map.get_stack(swamp).unwrap()[0].get_position(),swamp);
+        assert_eq!(map.contents_count(),10);
+
+        let unbounded : StackMap<Dummy,Bg> = StackMap::new((3,3),Position::default(),Capacity::Unbounded).unwrap();
+        assert!(unbounded.capacity().allows(&Bg::default(),&[dummy("a"),dummy("b")]));
+    }
+
+    #[test]
+    fn hex_map(){
+        let mut map : StackMap<Dummy,Bg> = StackMap::new((5,1),Position::default(),Capacity::Fixed(2)).unwrap();
+        map.push_content(Position::new(2,0),dummy("a")).unwrap();
+        assert_eq!(HexMap::get(&map,Position::new(2,0)).unwrap().0.unwrap().name,"a");
+        assert!(map.find_path(Position::new(0,0),Position::new(4,0),BusyPolicy::Blocked,|_| Some(1)).is_some());
+        map.push_content(Position::new(2,0),dummy("b")).unwrap();
+        assert_eq!(HexMap::get(&map,Position::new(2,0)).unwrap().0.unwrap().name,"b");
+        assert!(map.find_path(Position::new(0,0),Position::new(4,0),BusyPolicy::Blocked,|_| Some(1)).is_none());
+    }
+}
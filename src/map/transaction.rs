@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::fmt;
+use error::{Error,Reason,Result};
+use pos::Position;
+use map::{Map,PositionAccessor,AllowContent};
+
+/// One operation of a `Transaction`
+#[derive(Debug)]
+pub enum Operation<T> {
+    /// See `Map::move_contents`
+    Move {from:Position,to:Position},
+    /// See `Map::swap_contents`
+    Swap(Position,Position),
+    /// See `Map::create_content`
+    Create(Position,T),
+    /// See `Map::extract_content`
+    Extract(Position)
+}
+
+/// Operations applied at once on a `Map` by `Map::apply`.
+///
+/// Operations are simultaneous: every content leaves its position before any content
+/// arrives, so that `A→B, B→C, C→A` is valid, as is moving a content to a position that
+/// another one leaves. A position can still be left by only one operation and reached by
+/// only one operation.
+///
+/// ```
+/// use hexgrid::{Map,Transaction,AllowContent,PositionAccessor};
+/// use hexgrid::pos::Position;
+/// # #[derive(Default)]
+/// # struct Ground;
+/// # impl AllowContent for Ground {
+/// #     fn is_content_allowed(&self) -> bool { true }
+/// # }
+/// # #[derive(Debug)]
+/// # struct Unit { position:Position }
+/// # impl PositionAccessor for Unit {
+/// #     fn set_position(&mut self,new_position:Position) { self.position = new_position; }
+/// #     fn get_position(&self) -> Position { self.position }
+/// # }
+/// let (a,b,c) = (Position::new(0,0),Position::new(1,0),Position::new(2,0));
+/// let mut map : Map<Unit,Ground> = Map::new((3,1),a).unwrap();
+/// map.create_content(a,Unit {position:a}).unwrap();
+/// map.create_content(b,Unit {position:b}).unwrap();
+///
+/// let mut transaction = Transaction::new();
+/// transaction.move_contents(a,b).move_contents(b,c);
+/// map.apply(transaction).unwrap();
+/// assert!(map.get_contents(a).unwrap().is_none());
+/// assert_eq!(map.get_contents(c).unwrap().as_ref().unwrap().get_position(),c);
+/// ```
+#[derive(Debug)]
+pub struct Transaction<T> {
+    operations : Vec<Operation<T>>
+}
+
+impl<T> Default for Transaction<T> {
+    fn default() -> Transaction<T> {
+        Transaction::new()
+    }
+}
+
+impl<T> Transaction<T> {
+    pub fn new() -> Transaction<T> {
+        Transaction {operations:Vec::new()}
+    }
+
+    pub fn push(&mut self,operation:Operation<T>) -> &mut Transaction<T> {
+        self.operations.push(operation);
+        self
+    }
+
+    pub fn move_contents(&mut self,from:Position,to:Position) -> &mut Transaction<T> {
+        self.push(Operation::Move {from,to})
+    }
+
+    pub fn swap_contents(&mut self,pos_1:Position,pos_2:Position) -> &mut Transaction<T> {
+        self.push(Operation::Swap(pos_1,pos_2))
+    }
+
+    pub fn create_content(&mut self,position:Position,new_content:T) -> &mut Transaction<T> {
+        self.push(Operation::Create(position,new_content))
+    }
+
+    pub fn extract_content(&mut self,position:Position) -> &mut Transaction<T> {
+        self.push(Operation::Extract(position))
+    }
+
+    pub fn operations(&self) -> &[Operation<T>] {
+        &self.operations
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
+
+impl<T> From<Vec<Operation<T>>> for Transaction<T> {
+    fn from(operations:Vec<Operation<T>>) -> Transaction<T> {
+        Transaction {operations}
+    }
+}
+
+/// Why `Map::apply` failed: the error of every faulty operation, by index in the
+/// transaction, and the transaction itself so that created contents are not lost.
+#[derive(Debug)]
+pub struct TransactionError<T> {
+    errors : Vec<(usize,Error)>,
+    transaction : Transaction<T>
+}
+
+impl<T> TransactionError<T> {
+    pub fn errors(&self) -> &[(usize,Error)] {
+        &self.errors
+    }
+
+    pub fn into_transaction(self) -> Transaction<T> {
+        self.transaction
+    }
+}
+
+impl<T> fmt::Display for TransactionError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid transaction")?;
+        for (i,&(operation,error)) in self.errors.iter().enumerate() {
+            write!(f, "{} operation {}: {}", if i == 0 { ':' } else { ',' }, operation, error)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> ::std::error::Error for TransactionError<T> where T : fmt::Debug {}
+
+/// Indexes of the positions an operation leaves and reaches
+struct Touched {
+    left : Vec<usize>,
+    reached : Vec<usize>
+}
+
+impl<T,Bg> Map<T,Bg> where T : PositionAccessor, Bg : Default + AllowContent {
+    fn touched_by(&self,operation:&Operation<T>) -> Result<Touched> {
+        let touched = match *operation {
+            Operation::Move {from,to} => Touched {
+                left:vec![self.pos_to_index(from)?],
+                reached:vec![self.pos_to_index(to)?]
+            },
+            Operation::Swap(pos_1,pos_2) => {
+                let both = vec![self.pos_to_index(pos_1)?,self.pos_to_index(pos_2)?];
+                Touched {left:both.clone(),reached:both}
+            },
+            Operation::Create(position,_) => Touched {left:vec![],reached:vec![self.pos_to_index(position)?]},
+            Operation::Extract(position) => Touched {left:vec![self.pos_to_index(position)?],reached:vec![]},
+        };
+        Ok(touched)
+    }
+
+    /// Checks every operation of `transaction` as `apply` would, without applying them.
+    ///
+    /// # Errors
+    ///
+    /// The error of every faulty operation, by index in the transaction; the first one
+    /// found for each of them among:
+    ///
+    /// * `OutOfRange` if a position is not valid
+    /// * `ConflictingOperations` if a position is left, or reached, by an earlier operation
+    ///   as well (an operation can leave and reach the same position)
+    /// * `MissingTarget` if a position to leave has no content
+    /// * `ForbiddenLocation` if the `Bg` of a position reached by a move or a creation does
+    ///   not allow contents
+    /// * `AlreadyOccupied` if a position reached by a move or a creation has a content
+    ///   which is not leaving
+    pub fn check_transaction(&self,transaction:&Transaction<T>) -> ::std::result::Result<(),Vec<(usize,Error)>> {
+        let mut touched_by = Vec::with_capacity(transaction.len());
+        let mut errors = Vec::new();
+        let mut leaving : HashMap<usize,usize> = HashMap::new();
+        let mut reaching : HashMap<usize,usize> = HashMap::new();
+        for (i,operation) in transaction.operations.iter().enumerate() {
+            match self.touched_by(operation) {
+                Ok(touched) => {
+                    let mut conflict = false;
+                    for &index in &touched.left {
+                        conflict |= *leaving.entry(index).or_insert(i) != i;
+                    }
+                    for &index in &touched.reached {
+                        conflict |= *reaching.entry(index).or_insert(i) != i;
+                    }
+                    if conflict {
+                        errors.push((i,Error::new(Reason::ConflictingOperations)));
+                    }
+                    touched_by.push(Some(touched));
+                },
+                Err(err) => {
+                    errors.push((i,err));
+                    touched_by.push(None);
+                },
+            }
+        }
+        for (i,(operation,touched)) in transaction.operations.iter().zip(touched_by).enumerate() {
+            let touched = match touched {
+                Some(ref touched) if !errors.iter().any(|&(j,_)| j == i) => touched,
+                _ => continue,
+            };
+            if touched.left.iter().any(|&index| self.contents_slice[index].is_none()) {
+                errors.push((i,Error::new(Reason::MissingTarget)));
+                continue;
+            }
+            if let Operation::Swap(..) = *operation {
+                continue;
+            }
+            for &index in &touched.reached {
+                if !self.bg_slice[index].is_content_allowed() {
+                    errors.push((i,Error::new(Reason::ForbiddenLocation)));
+                } else if self.contents_slice[index].is_some() && !leaving.contains_key(&index) {
+                    errors.push((i,Error::new(Reason::AlreadyOccupied)));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            errors.sort_by_key(|&(i,_)| i);
+            Err(errors)
+        }
+    }
+
+    /// Applies every operation of `transaction` at once, or none of them if any is
+    /// faulty (see `check_transaction`).
+    ///
+    /// Returns the extracted contents, in the order of their operations. `set_position` is
+    /// called on every content which is moved, swapped or created.
+    pub fn apply(&mut self,transaction:Transaction<T>) -> ::std::result::Result<Vec<T>,TransactionError<T>> {
+        if let Err(errors) = self.check_transaction(&transaction) {
+            return Err(TransactionError {errors,transaction});
+        }
+        let mut extracted = Vec::new();
+        let mut arriving = Vec::new();
+        for operation in transaction.operations {
+            match operation {
+                Operation::Move {from,to} => {
+                    let content = self.contents_slice[self.pos_to_index(from).unwrap()].take();
+                    arriving.push((self.pos_to_index(to).unwrap(),content.unwrap()));
+                },
+                Operation::Swap(pos_1,pos_2) => {
+                    let (index_1,index_2) = (self.pos_to_index(pos_1).unwrap(),self.pos_to_index(pos_2).unwrap());
+                    if index_1 == index_2 {
+                        // like `Map::swap_contents`, swapping a content with itself does nothing
+                        continue;
+                    }
+                    let content_1 = self.contents_slice[index_1].take().unwrap();
+                    let content_2 = self.contents_slice[index_2].take().unwrap();
+                    arriving.push((index_2,content_1));
+                    arriving.push((index_1,content_2));
+                },
+                Operation::Create(position,content) => {
+                    arriving.push((self.pos_to_index(position).unwrap(),content));
+                },
+                Operation::Extract(position) => {
+                    let index = self.pos_to_index(position).unwrap();
+                    extracted.push(self.contents_slice[index].take().unwrap());
+                },
+            }
+        }
+        for (index,mut content) in arriving {
+            content.set_position(self.index_to_pos(index).unwrap());
+            self.contents_slice[index] = Some(content);
+        }
+        Ok(extracted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use map::tests::{Dummy,Bg,sample_map};
+
+    fn dummy(name:&str) -> Dummy {
+        Dummy {pos:Position::default(),name:String::from(name)}
+    }
+
+    fn name_at(map:&Map<Dummy,Bg>,position:Position) -> Option<&str> {
+        map.get_contents(position).unwrap().as_ref().map(|dummy| {
+            assert_eq!(dummy.get_position(),position);
+            dummy.name.as_str()
+        })
+    }
+
+    fn reasons(error:&TransactionError<Dummy>) -> Vec<(usize,Reason)> {
+        error.errors().iter().map(|&(i,err)| (i,err.reason())).collect()
+    }
+
+    #[test]
+    fn cycle(){
+        let mut map = sample_map();
+        let (a,b,c,d) = (Position::new(0,0),Position::new(1,0),Position::new(1,-1),Position::new(0,-1));
+        for &(position,name) in &[(a,"a"),(b,"b"),(c,"c"),(d,"d")] {
+            map.create_content(position,dummy(name)).unwrap();
+        }
+        let mut transaction = Transaction::new();
+        transaction.move_contents(a,b).move_contents(b,c).move_contents(c,a)
+                   .extract_content(d).create_content(d,dummy("e"));
+        let extracted = map.apply(transaction).unwrap();
+        assert_eq!(extracted.iter().map(|dummy| dummy.name.as_str()).collect::<Vec<_>>(),vec!["d"]);
+        assert_eq!((name_at(&map,a),name_at(&map,b),name_at(&map,c),name_at(&map,d)),
+                   (Some("c"),Some("a"),Some("b"),Some("e")));
+
+        let mut transaction = Transaction::new();
+        transaction.swap_contents(a,b).move_contents(c,Position::new(2,2)).move_contents(d,c);
+        map.apply(transaction).unwrap();
+        assert_eq!((name_at(&map,a),name_at(&map,b),name_at(&map,c),name_at(&map,d),name_at(&map,Position::new(2,2))),
+                   (Some("a"),Some("c"),Some("e"),None,Some("b")));
+    }
+
+    #[test]
+    fn all_or_nothing(){
+        let mut map = sample_map();
+        let (a,b,c) = (Position::new(0,0),Position::new(1,0),Position::new(2,0));
+        map.create_content(a,dummy("a")).unwrap();
+        map.create_content(b,dummy("b")).unwrap();
+        map.get_bg_mut(Position::new(3,0)).unwrap().kind = String::from("Obstacle");
+        let mut transaction = Transaction::new();
+        transaction.move_contents(a,c)                  // fine on its own
+                   .move_contents(b,c)                  // c is already reached
+                   .move_contents(c,a)                  // c is empty
+                   .create_content(b,dummy("new"))      // fine, since b is left
+                   .create_content(Position::new(3,0),dummy("new"))
+                   .extract_content(Position::new(9,9))
+                   .swap_contents(b,Position::new(-1,0));
+        let error = map.apply(transaction).unwrap_err();
+        assert_eq!(reasons(&error),vec![(1,Reason::ConflictingOperations),
+                                        (2,Reason::MissingTarget),
+                                        (4,Reason::ForbiddenLocation),
+                                        (5,Reason::OutOfRange),
+                                        (6,Reason::ConflictingOperations)]);
+        assert_eq!(error.into_transaction().len(),7);
+        assert_eq!((name_at(&map,a),name_at(&map,b),name_at(&map,c)),(Some("a"),Some("b"),None));
+
+        let mut transaction = Transaction::new();
+        transaction.create_content(b,dummy("new"));
+        let error = map.apply(transaction).unwrap_err();
+        assert_eq!(reasons(&error),vec![(0,Reason::AlreadyOccupied)]);
+        match error.into_transaction().operations()[0] {
+            Operation::Create(_,ref dummy) => assert_eq!(dummy.name,"new"),
+            _ => unreachable!(),
+        }
+        assert!(map.check_transaction(&Transaction::new()).is_ok());
+    }
+
+    #[test]
+    fn display(){
+        let mut map = sample_map();
+        let mut transaction = Transaction::new();
+        transaction.extract_content(Position::new(0,0)).extract_content(Position::new(9,9));
+        let error = map.apply(transaction).unwrap_err();
+        assert_eq!(error.to_string(),format!("invalid transaction: operation 0: {}, operation 1: {}",
+                                             Error::new(Reason::MissingTarget),Error::new(Reason::OutOfRange)));
+    }
+
+    #[test]
+    fn swap_with_itself(){
+        let mut map = sample_map();
+        let (a,b) = (Position::new(0,0),Position::new(1,0));
+        map.create_content(a,dummy("a")).unwrap();
+        let mut transaction = Transaction::new();
+        transaction.swap_contents(a,a);
+        assert!(map.apply(transaction).unwrap().is_empty());
+        assert_eq!(name_at(&map,a),Some("a"));
+
+        let mut transaction = Transaction::new();
+        transaction.swap_contents(b,b);
+        assert_eq!(reasons(&map.apply(transaction).unwrap_err()),vec![(0,Reason::MissingTarget)]);
+    }
+}
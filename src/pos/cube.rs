@@ -0,0 +1,193 @@
+use std::ops::{Sub,Add,Mul,Neg};
+use std::convert::From;
+use std::cmp::{max,min};
+use error::{Error,Reason,Result};
+use pos::Position;
+
+/// Cube representation of a `Position`.
+///
+/// The three coordinates always verify `x + y + z == 0`, which is why they can only be
+/// read through getters. `x` and `y` are the same as the ones of `Position`, and `z` is
+/// the one returned by `Position::get_z`.
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Default)]
+pub struct CubePosition {
+    x : i32,
+    y : i32,
+    z : i32
+}
+
+impl CubePosition {
+    /// # Errors
+    ///
+    /// * `InvalidCoordinates` if `x + y + z != 0`
+    pub fn new(x:i32,y:i32,z:i32) -> Result<CubePosition> {
+        if x + y + z != 0 {
+            Err(Error::new(Reason::InvalidCoordinates))
+        } else {
+            Ok(CubePosition {x,y,z})
+        }
+    }
+
+    pub fn get_x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> i32 {
+        self.y
+    }
+
+    pub fn get_z(&self) -> i32 {
+        self.z
+    }
+
+    /// Biggest of the three coordinates
+    pub fn max_axis(&self) -> i32 {
+        max(self.x,max(self.y,self.z))
+    }
+
+    /// Smallest of the three coordinates
+    pub fn min_axis(&self) -> i32 {
+        min(self.x,min(self.y,self.z))
+    }
+
+    /// Number of steps needed to go from the origin to this position.
+    ///
+    /// ```
+    /// use hexgrid::pos::CubePosition;
+    /// assert_eq!(CubePosition::new(2,-3,1).unwrap().length(),3);
+    /// ```
+    pub fn length(&self) -> i32 {
+        max(self.max_axis(),-self.min_axis())
+    }
+
+    pub fn distance_to(&self,other:CubePosition) -> i32 {
+        (other - *self).length()
+    }
+}
+
+impl Add for CubePosition {
+    type Output = Self;
+    fn add(self, rhs:Self) -> Self {
+        CubePosition {x:self.x + rhs.x, y:self.y + rhs.y, z:self.z + rhs.z}
+    }
+}
+
+impl Sub for CubePosition {
+    type Output = Self;
+    fn sub(self, rhs:Self) -> Self {
+        CubePosition {x:self.x - rhs.x, y:self.y - rhs.y, z:self.z - rhs.z}
+    }
+}
+
+impl Neg for CubePosition {
+    type Output = Self;
+    fn neg(self) -> Self {
+        CubePosition {x:-self.x, y:-self.y, z:-self.z}
+    }
+}
+
+impl Mul<i32> for CubePosition {
+    type Output = Self;
+    fn mul(self, rhs:i32) -> Self {
+        CubePosition {x:self.x * rhs, y:self.y * rhs, z:self.z * rhs}
+    }
+}
+
+impl Mul<CubePosition> for i32 {
+    type Output = CubePosition;
+    fn mul(self, rhs:CubePosition) -> CubePosition {
+        rhs * self
+    }
+}
+
+impl From<Position> for CubePosition {
+    fn from(position:Position) -> CubePosition {
+        CubePosition {x:position.x, y:position.y, z:position.get_z()}
+    }
+}
+
+impl From<CubePosition> for Position {
+    fn from(cube:CubePosition) -> Position {
+        Position::new(cube.x,cube.y)
+    }
+}
+
+impl Position {
+    pub fn to_cube(self) -> CubePosition {
+        CubePosition::from(self)
+    }
+
+    /// Number of steps needed to go from the origin to this position.
+    pub fn length(self) -> i32 {
+        self.to_cube().length()
+    }
+
+    /// Number of steps needed to go from this position to `other`.
+    ///
+    /// ```
+    /// use hexgrid::pos::Position;
+    /// assert_eq!(Position::new(1,1).distance_to(Position::new(-1,0)),3);
+    /// ```
+    pub fn distance_to(self,other:Position) -> i32 {
+        (other - self).length()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pos::* ;
+    use error::{Error,Reason};
+
+    #[test]
+    fn new_cube(){
+        assert!(CubePosition::new(1,-2,1).is_ok());
+        assert_eq!(CubePosition::new(1,1,1).unwrap_err(),
+                   Error::new(Reason::InvalidCoordinates));
+    }
+
+    #[test]
+    fn cube_conversion(){
+        let position = Position::new(5,-2);
+        let cube = position.to_cube();
+        assert_eq!((cube.get_x(),cube.get_y(),cube.get_z()),(5,-2,-3));
+        assert_eq!(Position::from(cube),position);
+    }
+
+    #[test]
+    fn cube_arithmetic(){
+        let a = CubePosition::new(1,-1,0).unwrap();
+        let b = CubePosition::new(0,2,-2).unwrap();
+        assert_eq!(a + b,CubePosition::new(1,1,-2).unwrap());
+        assert_eq!(a - b,CubePosition::new(1,-3,2).unwrap());
+        assert_eq!(-a,CubePosition::new(-1,1,0).unwrap());
+        assert_eq!(2 * b,CubePosition::new(0,4,-4).unwrap());
+    }
+
+    #[test]
+    fn axis(){
+        let cube = CubePosition::new(4,-1,-3).unwrap();
+        assert_eq!(cube.max_axis(),4);
+        assert_eq!(cube.min_axis(),-3);
+    }
+
+    #[test]
+    fn length(){
+        assert_eq!(Position::new(0,0).length(),0);
+        assert_eq!(NE.length(),1);
+        assert_eq!(SE.length(),1);
+        assert_eq!(Position::new(3,-1).length(),3);
+        assert_eq!(Position::new(-2,-2).length(),4);
+    }
+
+    #[test]
+    fn distance(){
+        let a = Position::new(2,-3);
+        let b = Position::new(-1,1);
+        assert_eq!(a.distance_to(b),4);
+        assert_eq!(b.distance_to(a),4);
+        assert_eq!(a.distance_to(a),0);
+        for position in Position::new(3,2).in_range(3) {
+            assert!(position.distance_to(Position::new(3,2)) <= 3);
+        }
+    }
+}
@@ -2,18 +2,21 @@ use std::ops::Neg;
 use pos::*;
 
 #[derive(Copy,Clone,Debug,PartialEq,Eq)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
 pub enum Rotation {
     Clockwise,
     CounterClockwise
 }
 
 #[derive(Copy,Clone,Debug,PartialEq,Eq)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
 pub enum Direction {
     MainDirection(MainDirection),
     SubDirection(SubDirection)
 }
 
 #[derive(Copy,Clone,Debug,PartialEq,Eq)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
 pub enum MainDirection {
     NNE,
     E,
@@ -51,6 +54,7 @@ impl MainDirection {
 }
 
 #[derive(Copy,Clone,Debug,PartialEq,Eq)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
 pub enum SubDirection {
     N,
     ENE,
@@ -103,6 +107,7 @@ impl Neg for MainDirection {
 }
 
 #[derive(Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
 pub struct BaseVec(pub MainDirection,pub i32);
 
 /// ```
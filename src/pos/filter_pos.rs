@@ -1,69 +1,93 @@
 use std::vec::Vec ;
 use pos::*;
-use std::cmp::{max,min};
 impl Position {
     /// Positions from top-left are added first, to top-right, to finish by bottom-right
     pub fn in_range(self,range:i32) -> Vec<Position> {
-        let range = range.abs() ;
-        let n : usize = range.abs() as usize + 1 ;
-        let mut vec : Vec<Position> = Vec::with_capacity(n*(n-1)*3 + 1);
-        // formula is (n/2)*(n-1)*6 + 1
-        for dy in -range .. range + 1 {
-            for dx in (max(-range,-range-dy) .. min(range,range-dy) + 1).rev() {
-                vec.push(Position::new(self.x + dx,self.y + dy));
-            }
-        };
-        vec
+        self.iter_range(range).collect()
     }
 
     pub fn in_star_edges(self,range:i32) -> Vec<Position> {
-        let mut vec : Vec<Position> = Vec::with_capacity(6);
-        vec.push(self + (NE * range));
-        vec.push(self + (E  * range));
-        vec.push(self + (SE * range));
-        vec.push(self + (NW * range));
-        vec.push(self + (W  * range));
-        vec.push(self + (SW * range));
-        vec
+        vec![self + (NE * range),
+             self + (E  * range),
+             self + (SE * range),
+             self + (NW * range),
+             self + (W  * range),
+             self + (SW * range)]
     }
 
     /// Center of the star is added first, and
     pub fn in_star(self,range:i32) -> Vec<Position> {
-        let range = range.abs() ;
-        let mut vec : Vec<Position> = Vec::with_capacity(6 * (range as usize) + 1);
-        vec.push(self);
-        for r in 1..range + 1 {
-            vec.extend_from_slice(self.in_star_edges(r).as_slice());
-        }
-        vec
+        self.iter_star(range).collect()
     }
 
     pub fn in_cone(self,direction:MainDirection,range:i32) -> Vec<Position> {
-        let (direction,range) = BaseVec(direction,range).normalize().raw();
-        let n = (range + 1) as usize;
-        let mut vec : Vec<Position> = Vec::with_capacity((n+1) * (n+1));
-        for r in 0..range+1 { // r = current_range
-            let temp_pos = self + direction.to_pos() * r ;
-            vec.push(temp_pos);
-            if r != 0 {
-                use pos::Rotation::{CounterClockwise as CCW, Clockwise as CW};
-                let (direction_1,direction_2) = (direction.rotate(CW).rotate(CW),direction.rotate(CCW).rotate(CCW));
-                for i in 1..r+1 {
-                    vec.push(temp_pos + direction_1.to_pos() * i);
-                    vec.push(temp_pos + direction_2.to_pos() * i);
-                }
-            }
-        }
-        vec
+        self.iter_cone(direction,range).collect()
+    }
+
+    /// Every position at exactly `radius` steps of `self`.
+    ///
+    /// Same as `ring_with(radius,MainDirection::NNE,Rotation::Clockwise)`: the ring starts
+    /// at `self + NE * radius` and goes clockwise, so index `i` of the result is always
+    /// the same position for a given center and radius.
+    pub fn ring(self,radius:i32) -> Vec<Position> {
+        self.iter_ring(radius).collect()
+    }
+
+    /// Every position at exactly `radius` steps of `self`, starting at
+    /// `self + start.to_pos() * radius` and turning around `self` in the `rotation` way.
+    ///
+    /// The ring of radius 0 is `self` alone, any other ring has `6 * radius` positions.
+    ///
+    /// ```
+    /// use hexgrid::pos::*;
+    /// let ring = Position::new(0,0).ring_with(1,MainDirection::E,Rotation::CounterClockwise);
+    /// assert_eq!(ring,vec![E,NE,NW,W,SW,SE]);
+    /// ```
+    pub fn ring_with(self,radius:i32,start:MainDirection,rotation:Rotation) -> Vec<Position> {
+        self.iter_ring_with(radius,start,rotation).collect()
+    }
+
+    /// `self`, followed by every ring of radius 1 up to `radius` (see `ring`).
+    ///
+    /// Contains the same positions as `in_range(radius)`, but sorted by distance to `self`.
+    pub fn spiral(self,radius:i32) -> Vec<Position> {
+        self.iter_spiral(radius).collect()
+    }
+
+    /// `self`, followed by every ring of radius 1 up to `radius` (see `ring_with`).
+    pub fn spiral_with(self,radius:i32,start:MainDirection,rotation:Rotation) -> Vec<Position> {
+        self.iter_spiral_with(radius,start,rotation).collect()
     }
 
     pub fn in_line(self,direction:MainDirection,range:i32) -> Vec<Position> {
-        let mut vec : Vec<Position> = Vec::with_capacity(range.abs() as usize + 1);
-        let (direction,range) = BaseVec(direction,range).normalize().raw();
-        for i in 0..range+1 {
-            vec.push(self + direction.to_pos() * i);
+        self.iter_line(direction,range).collect()
+    }
+
+    /// Whether `self` is one of `center.in_range(range)`
+    pub fn is_in_range(self,center:Position,range:i32) -> bool {
+        self.distance_to(center) <= range.abs()
+    }
+
+    /// Whether `self` is one of `center.in_cone(direction,range)`
+    ///
+    /// ```
+    /// use hexgrid::pos::*;
+    /// let center = Position::new(1,1);
+    /// assert!((center + NE * 2 + E).is_in_cone(center,MainDirection::NNE,3));
+    /// assert!(!(center + NE * 2 + E).is_in_cone(center,MainDirection::W,3));
+    /// ```
+    pub fn is_in_cone(self,center:Position,direction:MainDirection,range:i32) -> bool {
+        let (mut direction,range) = BaseVec(direction,range).normalize().raw();
+        // turn the cone until it points to `E`, its positions then have either their `x`
+        // or their `-z` as cube length
+        let mut relative = self - center;
+        while direction != MainDirection::E {
+            direction = direction.rotate(Rotation::Clockwise);
+            relative = relative.rotate(Rotation::Clockwise);
         }
-        vec
+        let cube = relative.to_cube();
+        let length = cube.length();
+        length <= range && (cube.get_x() == length || -cube.get_z() == length)
     }
 }
 
@@ -84,6 +108,25 @@ mod tests {
     fn test_in_range(){
         let v = Position::new(0,0).in_range(2);
         assert_eq!(v.len(),19);
+        let center = Position::new(3,-1);
+        let range = center.in_range(3);
+        for position in Position::new(3,-1).in_range(5) {
+            assert_eq!(position.is_in_range(center,3),range.contains(&position));
+        }
+    }
+
+    #[test]
+    fn test_is_in_cone(){
+        let center = Position::new(-2,1);
+        for &direction in &[MainDirection::NNE,MainDirection::E,MainDirection::SSE,
+                            MainDirection::SSW,MainDirection::W,MainDirection::NNW] {
+            for &range in &[0,2,-3] {
+                let cone = center.in_cone(direction,range);
+                for position in center.in_range(5) {
+                    assert_eq!(position.is_in_cone(center,direction,range),cone.contains(&position));
+                }
+            }
+        }
     }
 
     #[test]
@@ -92,6 +135,54 @@ mod tests {
         assert_eq!(v.len(),13);
     }
 
+    #[test]
+    fn test_ring(){
+        let center = Position::new(2,-1);
+        assert_eq!(center.ring(0),vec![center]);
+        let ring = center.ring(1);
+        assert_eq!(ring,vec![center + NE,center + E,center + SE,
+                             center + SW,center + W,center + NW]);
+        let ring = center.ring(3);
+        assert_eq!(ring.len(),18);
+        assert_eq!(ring[0],center + NE * 3);
+        assert_eq!(ring[3],center + E * 3);
+        for position in &ring {
+            assert_eq!(position.distance_to(center),3);
+        }
+        for pair in ring.windows(2) {
+            assert_eq!(pair[0].distance_to(pair[1]),1);
+        }
+        assert_eq!(ring[17].distance_to(ring[0]),1);
+    }
+
+    #[test]
+    fn test_ring_with(){
+        let center = Position::new(0,0);
+        let cw = center.ring_with(2,MainDirection::W,Rotation::Clockwise);
+        let mut ccw = center.ring_with(2,MainDirection::W,Rotation::CounterClockwise);
+        assert_eq!(cw[0],W * 2);
+        assert_eq!(cw[1],W * 2 + NE);
+        ccw[1..].reverse();
+        assert_eq!(cw,ccw);
+    }
+
+    #[test]
+    fn test_spiral(){
+        let center = Position::new(1,1);
+        let spiral = center.spiral(3);
+        let mut range = center.in_range(3);
+        assert_eq!(spiral.len(),range.len());
+        assert_eq!(spiral[0],center);
+        assert_eq!(spiral[1..7].to_vec(),center.ring(1));
+        for pair in spiral.windows(2) {
+            assert!(pair[0].distance_to(center) <= pair[1].distance_to(center));
+        }
+        let mut sorted_spiral = spiral.clone();
+        sorted_spiral.sort_by_key(|p| (p.x,p.y));
+        range.sort_by_key(|p| (p.x,p.y));
+        assert_eq!(sorted_spiral,range);
+    }
+
     #[test]
     fn test_in_line(){
         let v = Position::new(0,0).in_line(MainDirection::NNE,3);
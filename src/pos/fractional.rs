@@ -0,0 +1,190 @@
+use std::ops::{Sub,Add,Mul,Neg};
+use std::convert::From;
+use pos::Position;
+
+/// Default nudge used by `Position::line_to`.
+pub const LINE_EPSILON : f64 = 1e-6 ;
+
+/// A `Position` whose coordinates are not necessarily integers.
+///
+/// Mostly useful as an intermediate result (interpolation, pixel to hex conversion...),
+/// use `round` to get back the `Position` which contains it.
+#[derive(Copy,Clone,Debug,PartialEq,Default)]
+pub struct FractionalPosition {
+    pub x : f64,
+    pub y : f64
+}
+
+impl FractionalPosition {
+    pub fn new(x:f64,y:f64) -> FractionalPosition {
+        FractionalPosition {x,y}
+    }
+
+    pub fn get_z(&self) -> f64 {
+        - self.x - self.y
+    }
+
+    /// Linear interpolation between `self` (`t = 0`) and `other` (`t = 1`)
+    pub fn lerp(self,other:FractionalPosition,t:f64) -> FractionalPosition {
+        self + (other - self) * t
+    }
+
+    /// Returns the `Position` of the hex containing this point.
+    ///
+    /// Every cube coordinate is rounded, and the one which moved the most is then
+    /// recomputed from the two others so that `x + y + z == 0` still holds.
+    ///
+    /// ```
+    /// use hexgrid::pos::{Position,FractionalPosition};
+    /// assert_eq!(FractionalPosition::new(0.6,0.3).round(),Position::new(1,0));
+    /// assert_eq!(FractionalPosition::new(0.4,0.4).round(),Position::new(0,1));
+    /// ```
+    pub fn round(self) -> Position {
+        let z = self.get_z();
+        let (mut rx, mut ry, rz) = (self.x.round(), self.y.round(), z.round());
+        let (dx, dy, dz) = ((rx - self.x).abs(), (ry - self.y).abs(), (rz - z).abs());
+        if dx > dy && dx > dz {
+            rx = - ry - rz ;
+        } else if dy > dz {
+            ry = - rx - rz ;
+        }
+        Position::new(rx as i32,ry as i32)
+    }
+}
+
+impl Add for FractionalPosition {
+    type Output = Self;
+    fn add(self, rhs:Self) -> Self {
+        FractionalPosition::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for FractionalPosition {
+    type Output = Self;
+    fn sub(self, rhs:Self) -> Self {
+        FractionalPosition::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl Neg for FractionalPosition {
+    type Output = Self;
+    fn neg(self) -> Self {
+        FractionalPosition::new(-self.x, -self.y)
+    }
+}
+
+impl Mul<f64> for FractionalPosition {
+    type Output = Self;
+    fn mul(self, rhs:f64) -> Self {
+        FractionalPosition::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl From<Position> for FractionalPosition {
+    fn from(position:Position) -> FractionalPosition {
+        FractionalPosition::new(position.x as f64, position.y as f64)
+    }
+}
+
+impl Position {
+    /// Every `Position` crossed by the segment going from `self` to `other`, both
+    /// included, in order.
+    ///
+    /// Same as `line_to_with_nudge(other,LINE_EPSILON)`.
+    ///
+    /// ```
+    /// use hexgrid::pos::Position;
+    /// let line = Position::new(0,0).line_to(Position::new(3,-1));
+    /// assert_eq!(line.len(),4);
+    /// assert_eq!(line[0],Position::new(0,0));
+    /// assert_eq!(line[3],Position::new(3,-1));
+    /// ```
+    pub fn line_to(self,other:Position) -> Vec<Position> {
+        self.line_to_with_nudge(other,LINE_EPSILON)
+    }
+
+    /// Every `Position` crossed by the segment going from `self` to `other`, both
+    /// included, in order.
+    ///
+    /// When the segment runs exactly along the edge between two hexes, the sampled points
+    /// are at equal distance of both and the result depends on how rounding breaks the
+    /// tie. Both ends are moved by `(epsilon, 2 * epsilon, -3 * epsilon)` (in cube
+    /// coordinates) beforehand so that the same side is always picked; a negative
+    /// `epsilon` picks the other side, and `0.0` disables the nudge entirely.
+    pub fn line_to_with_nudge(self,other:Position,epsilon:f64) -> Vec<Position> {
+        let n = self.distance_to(other);
+        let nudge = FractionalPosition::new(epsilon, 2.0 * epsilon);
+        let from = FractionalPosition::from(self) + nudge;
+        let to = FractionalPosition::from(other) + nudge;
+        let mut vec : Vec<Position> = Vec::with_capacity(n as usize + 1);
+        vec.push(self);
+        for i in 1..n {
+            vec.push(from.lerp(to, i as f64 / n as f64).round());
+        }
+        if n > 0 {
+            vec.push(other);
+        }
+        vec
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pos::* ;
+
+    #[test]
+    fn lerp(){
+        let a = FractionalPosition::new(0.0,0.0);
+        let b = FractionalPosition::new(4.0,-2.0);
+        assert_eq!(a.lerp(b,0.5),FractionalPosition::new(2.0,-1.0));
+        assert_eq!(a.lerp(b,0.0),a);
+        assert_eq!(a.lerp(b,1.0),b);
+    }
+
+    #[test]
+    fn round(){
+        for position in Position::new(0,0).in_range(3) {
+            let f = FractionalPosition::from(position);
+            assert_eq!(f.round(),position);
+            assert_eq!((f + FractionalPosition::new(0.2,-0.1)).round(),position);
+        }
+    }
+
+    #[test]
+    fn line_along_axis(){
+        let origin = Position::new(1,-2);
+        for &direction in &[MainDirection::NNE,MainDirection::E,MainDirection::SSE,
+                            MainDirection::SSW,MainDirection::W,MainDirection::NNW] {
+            let target = origin + direction.to_pos() * 4;
+            assert_eq!(origin.line_to(target),origin.in_line(direction,4));
+        }
+    }
+
+    #[test]
+    fn line_is_connected(){
+        let origin = Position::new(-3,2);
+        for target in origin.in_range(5) {
+            let line = origin.line_to(target);
+            assert_eq!(line.len() as i32,origin.distance_to(target) + 1);
+            assert_eq!(*line.first().unwrap(),origin);
+            assert_eq!(*line.last().unwrap(),target);
+            for pair in line.windows(2) {
+                assert_eq!(pair[0].distance_to(pair[1]),1);
+            }
+        }
+    }
+
+    #[test]
+    fn line_along_edge(){
+        // (0,0) -> (1,1) runs along the edge between (1,0) and (0,1)
+        let origin = Position::new(0,0);
+        let target = Position::new(1,1);
+        let line = origin.line_to_with_nudge(target,LINE_EPSILON);
+        let other_line = origin.line_to_with_nudge(target,-LINE_EPSILON);
+        assert_eq!(line.len(),3);
+        assert_eq!(other_line.len(),3);
+        assert!(line[1] == Position::new(1,0) || line[1] == Position::new(0,1));
+        assert!(other_line[1] == Position::new(1,0) || other_line[1] == Position::new(0,1));
+        assert!(line[1] != other_line[1]);
+    }
+}
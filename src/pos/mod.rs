@@ -1,7 +1,17 @@
 pub use self::position::*;
 pub use self::direction::*;
+// only holds methods of `Position` for now, but stays part of the public paths
+#[allow(unused_imports)]
 pub use self::filter_pos::* ;
+pub use self::cube::*;
+pub use self::fractional::*;
+pub use self::shape_iter::*;
+pub use self::offset::*;
 
 mod direction;
 mod position ;
+mod cube ;
+mod fractional ;
 mod filter_pos ;
+mod shape_iter ;
+mod offset ;
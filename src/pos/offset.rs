@@ -0,0 +1,244 @@
+use std::convert::From;
+use error::{Error,Reason,Result};
+use pos::Position;
+
+/// Offset coordinate systems, as used by most tile editors.
+///
+/// Rows are counted downwards and columns rightwards, on the screen described in
+/// `layout` (so the row of a `Position` decreases when its `y` increases).
+/// `OddR` and `EvenR` are meant for `Orientation::Pointy` and shove every odd (or even)
+/// row to the right, `OddQ` and `EvenQ` are meant for `Orientation::Flat` and shove every
+/// odd (or even) column down.
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+pub enum OffsetKind {
+    OddR,
+    EvenR,
+    OddQ,
+    EvenQ
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+pub struct OffsetPosition {
+    pub col : i32,
+    pub row : i32,
+    pub kind : OffsetKind
+}
+
+impl OffsetPosition {
+    pub fn new(col:i32,row:i32,kind:OffsetKind) -> OffsetPosition {
+        OffsetPosition {col,row,kind}
+    }
+}
+
+/// Doubled coordinate systems.
+///
+/// `DoubledWidth` is meant for `Orientation::Pointy`: columns are doubled, and
+/// `col + row` is always even. `DoubledHeight` is meant for `Orientation::Flat`: rows are
+/// doubled, and `col + row` is always even as well.
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+pub enum DoubledKind {
+    DoubledWidth,
+    DoubledHeight
+}
+
+/// Position in doubled coordinates, see `DoubledKind`.
+///
+/// Half of the `(col,row)` pairs do not match any hex, which is why they can only be
+/// read through getters.
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+pub struct DoubledPosition {
+    col : i32,
+    row : i32,
+    kind : DoubledKind
+}
+
+impl DoubledPosition {
+    /// # Errors
+    ///
+    /// * `InvalidCoordinates` if `col + row` is odd
+    pub fn new(col:i32,row:i32,kind:DoubledKind) -> Result<DoubledPosition> {
+        if (col + row) & 1 != 0 {
+            Err(Error::new(Reason::InvalidCoordinates))
+        } else {
+            Ok(DoubledPosition {col,row,kind})
+        }
+    }
+
+    pub fn get_col(&self) -> i32 {
+        self.col
+    }
+
+    pub fn get_row(&self) -> i32 {
+        self.row
+    }
+
+    pub fn get_kind(&self) -> DoubledKind {
+        self.kind
+    }
+}
+
+// Conversions go through the axial coordinates of pointy hexes with `q` to the right and
+// `r` downwards (pointy) or through the ones of flat hexes with `q` down-right and `r`
+// downwards (flat), which are the usual bases of offset coordinates.
+
+impl From<OffsetPosition> for Position {
+    fn from(offset:OffsetPosition) -> Position {
+        let (col,row) = (offset.col,offset.row);
+        match offset.kind {
+            OffsetKind::OddR | OffsetKind::EvenR => {
+                let shift = if offset.kind == OffsetKind::OddR { - (row & 1) } else { row & 1 };
+                let (q,r) = (col - (row + shift) / 2, row);
+                Position::new(q + r,-r)
+            },
+            OffsetKind::OddQ | OffsetKind::EvenQ => {
+                let shift = if offset.kind == OffsetKind::OddQ { - (col & 1) } else { col & 1 };
+                let (q,r) = (col, row - (col + shift) / 2);
+                Position::new(q,- q - r)
+            },
+        }
+    }
+}
+
+impl From<DoubledPosition> for Position {
+    fn from(doubled:DoubledPosition) -> Position {
+        let (col,row) = (doubled.col,doubled.row);
+        match doubled.kind {
+            DoubledKind::DoubledWidth => {
+                let (q,r) = ((col - row) / 2, row);
+                Position::new(q + r,-r)
+            },
+            DoubledKind::DoubledHeight => {
+                let (q,r) = (col, (row - col) / 2);
+                Position::new(q,- q - r)
+            },
+        }
+    }
+}
+
+impl Position {
+    /// ```
+    /// use hexgrid::pos::*;
+    /// let offset = Position::new(2,-1).to_offset(OffsetKind::OddR);
+    /// assert_eq!((offset.col,offset.row),(1,1));
+    /// assert_eq!(Position::from(offset),Position::new(2,-1));
+    /// ```
+    pub fn to_offset(self,kind:OffsetKind) -> OffsetPosition {
+        match kind {
+            OffsetKind::OddR | OffsetKind::EvenR => {
+                let (q,r) = (self.x + self.y, - self.y);
+                let shift = if kind == OffsetKind::OddR { - (r & 1) } else { r & 1 };
+                OffsetPosition::new(q + (r + shift) / 2,r,kind)
+            },
+            OffsetKind::OddQ | OffsetKind::EvenQ => {
+                let (q,r) = (self.x, - self.x - self.y);
+                let shift = if kind == OffsetKind::OddQ { - (q & 1) } else { q & 1 };
+                OffsetPosition::new(q,r + (q + shift) / 2,kind)
+            },
+        }
+    }
+
+    pub fn to_doubled(self,kind:DoubledKind) -> DoubledPosition {
+        let (col,row) = match kind {
+            DoubledKind::DoubledWidth => {
+                let (q,r) = (self.x + self.y, - self.y);
+                (2 * q + r,r)
+            },
+            DoubledKind::DoubledHeight => {
+                let (q,r) = (self.x, - self.x - self.y);
+                (q,2 * r + q)
+            },
+        };
+        DoubledPosition {col,row,kind}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pos::* ;
+    use layout::*;
+    use error::{Error,Reason};
+
+    const OFFSET_KINDS : [OffsetKind;4] = [OffsetKind::OddR,OffsetKind::EvenR,
+                                           OffsetKind::OddQ,OffsetKind::EvenQ];
+    const DOUBLED_KINDS : [DoubledKind;2] = [DoubledKind::DoubledWidth,DoubledKind::DoubledHeight];
+
+    #[test]
+    fn offset_round_trip(){
+        for &kind in OFFSET_KINDS.iter() {
+            for position in Position::new(-3,2).iter_range(6) {
+                assert_eq!(Position::from(position.to_offset(kind)),position);
+            }
+            for col in -7..8 {
+                for row in -7..8 {
+                    let offset = OffsetPosition::new(col,row,kind);
+                    assert_eq!(Position::from(offset).to_offset(kind),offset);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn doubled_round_trip(){
+        for &kind in DOUBLED_KINDS.iter() {
+            for position in Position::new(1,-4).iter_range(6) {
+                assert_eq!(Position::from(position.to_doubled(kind)),position);
+            }
+            for col in -7..8 {
+                for row in -7..8 {
+                    match DoubledPosition::new(col,row,kind) {
+                        Ok(doubled) => assert_eq!(Position::from(doubled).to_doubled(kind),doubled),
+                        Err(err) => {
+                            assert_eq!(err,Error::new(Reason::InvalidCoordinates));
+                            assert!((col + row) % 2 != 0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn offset_matches_layout(){
+        let pointy = Layout::new(Orientation::Pointy,Point::new(1.0,1.0),Point::new(0.0,0.0));
+        let flat = Layout::new(Orientation::Flat,Point::new(1.0,1.0),Point::new(0.0,0.0));
+        for &kind in OFFSET_KINDS.iter() {
+            let (layout,rows) = match kind {
+                OffsetKind::OddR | OffsetKind::EvenR => (pointy,true),
+                _ => (flat,false)
+            };
+            for col in -4..5 {
+                for row in -4..5 {
+                    let a = layout.hex_to_pixel(Position::from(OffsetPosition::new(col,row,kind)));
+                    let right = layout.hex_to_pixel(Position::from(OffsetPosition::new(col + 1,row,kind)));
+                    let below = layout.hex_to_pixel(Position::from(OffsetPosition::new(col,row + 1,kind)));
+                    assert!(right.x > a.x);
+                    assert!(below.y > a.y);
+                    if rows {
+                        assert!((right.y - a.y).abs() < 1e-9);
+                    } else {
+                        assert!((below.x - a.x).abs() < 1e-9);
+                    }
+                }
+            }
+        }
+        let odd_row = Position::from(OffsetPosition::new(0,1,OffsetKind::OddR));
+        let even_row = Position::from(OffsetPosition::new(0,0,OffsetKind::OddR));
+        assert!(pointy.hex_to_pixel(odd_row).x > pointy.hex_to_pixel(even_row).x);
+    }
+
+    #[test]
+    fn doubled_matches_layout(){
+        let pointy = Layout::new(Orientation::Pointy,Point::new(1.0,1.0),Point::new(0.0,0.0));
+        let flat = Layout::new(Orientation::Flat,Point::new(1.0,1.0),Point::new(0.0,0.0));
+        for position in Position::new(0,0).iter_range(4) {
+            let doubled = position.to_doubled(DoubledKind::DoubledWidth);
+            let pixel = pointy.hex_to_pixel(position);
+            assert!((pixel.x - doubled.get_col() as f64 * 3f64.sqrt() / 2.0).abs() < 1e-9);
+            assert!((pixel.y - doubled.get_row() as f64 * 1.5).abs() < 1e-9);
+            let doubled = position.to_doubled(DoubledKind::DoubledHeight);
+            let pixel = flat.hex_to_pixel(position);
+            assert!((pixel.x - doubled.get_col() as f64 * 1.5).abs() < 1e-9);
+            assert!((pixel.y - doubled.get_row() as f64 * 3f64.sqrt() / 2.0).abs() < 1e-9);
+        }
+    }
+}
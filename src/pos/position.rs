@@ -6,8 +6,10 @@
 
 use std::ops::{Sub,Add,Mul,Neg};
 use std::convert::From;
+use pos::Rotation;
 
 #[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Default)]
+#[cfg_attr(feature="serde",derive(Serialize,Deserialize))]
 pub struct Position {
     pub x : i32,
     pub y : i32
@@ -22,7 +24,7 @@ pub const SW : Position = Position {x: 0,y:-1};
 
 impl Position {
     pub fn new(x:i32,y:i32) -> Position {
-        Position {x:x,y:y}
+        Position {x,y}
     }
 
     ///
@@ -38,6 +40,31 @@ impl Position {
     pub fn get_z(&self) -> i32 {
         - self.x - self.y
     }
+
+    /// The 6 adjacent positions, clockwise from the north-east one (same order as `ring(1)`)
+    pub fn neighbors(self) -> [Position;6] {
+        [self + NE, self + E, self + SE, self + SW, self + W, self + NW]
+    }
+
+    /// Rotates by a sixth of a turn around `(0,0)`, the same way as `MainDirection::rotate`.
+    ///
+    /// ```
+    /// use hexgrid::pos::{Position,MainDirection,Rotation};
+    /// assert_eq!(MainDirection::NNE.to_pos().rotate(Rotation::Clockwise),
+    ///            MainDirection::NNE.rotate(Rotation::Clockwise).to_pos());
+    /// assert_eq!(Position::new(2,1).rotate(Rotation::CounterClockwise),Position::new(-1,3));
+    /// ```
+    pub fn rotate(self,rotation:Rotation) -> Position {
+        match rotation {
+            Rotation::Clockwise => Position::new(self.x + self.y,-self.x),
+            Rotation::CounterClockwise => Position::new(-self.y,self.x + self.y),
+        }
+    }
+
+    /// Rotates by a sixth of a turn around `center`.
+    pub fn rotate_around(self,center:Position,rotation:Rotation) -> Position {
+        center + (self - center).rotate(rotation)
+    }
 }
 
 impl Add for Position {
@@ -160,6 +187,12 @@ mod tests {
         assert_eq!(-NW,SE);
     }
 
+    #[test]
+    fn neighbors(){
+        let position = Position::new(3,-2);
+        assert_eq!(position.neighbors().to_vec(),position.ring(1));
+    }
+
     #[test]
     fn arithmetic(){
         let position_init = Position::new(1,0) ;
@@ -167,4 +200,25 @@ mod tests {
         assert_eq!(position_init + (NE * 2) + (W*2) + (SE * 3), position_target);
     }
 
+
+    #[test]
+    fn rotate(){
+        use pos::MainDirection;
+        let directions = [MainDirection::NNE,MainDirection::E,MainDirection::SSE,
+                          MainDirection::SSW,MainDirection::W,MainDirection::NNW];
+        for &direction in &directions {
+            for &rotation in &[Rotation::Clockwise,Rotation::CounterClockwise] {
+                assert_eq!(direction.to_pos().rotate(rotation),direction.rotate(rotation).to_pos());
+                assert_eq!((direction.to_pos() * 3).rotate(rotation),direction.rotate(rotation).to_pos() * 3);
+            }
+        }
+        let mut position = Position::new(3,-7);
+        for _ in 0..6 {
+            assert_eq!(position.rotate(Rotation::Clockwise).rotate(Rotation::CounterClockwise),position);
+            assert_eq!(position.rotate(Rotation::Clockwise).length(),position.length());
+            position = position.rotate(Rotation::Clockwise);
+        }
+        assert_eq!(position,Position::new(3,-7));
+        assert_eq!(Position::new(2,1).rotate_around(Position::new(1,1),Rotation::Clockwise),Position::new(2,0));
+    }
 }
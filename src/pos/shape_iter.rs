@@ -0,0 +1,419 @@
+use std::iter::{Iterator,DoubleEndedIterator,ExactSizeIterator,FusedIterator};
+use std::cmp::{max,min};
+use pos::*;
+
+/// Implements `Iterator`, `DoubleEndedIterator`, `ExactSizeIterator` and `FusedIterator`
+/// for an iterator having `front` and `back` indexes and a `position_at(index)` method.
+macro_rules! indexed_shape_iter {
+    ($name:ident) => {
+        impl Iterator for $name {
+            type Item = Position ;
+            #[inline]
+            fn next(&mut self) -> Option<Position> {
+                if self.front < self.back {
+                    let position = self.position_at(self.front);
+                    self.front += 1;
+                    Some(position)
+                } else {
+                    None
+                }
+            }
+
+            #[inline]
+            fn size_hint(&self) -> (usize,Option<usize>) {
+                let len = self.back - self.front ;
+                (len,Some(len))
+            }
+
+            #[inline]
+            fn nth(&mut self,n:usize) -> Option<Position> {
+                self.front = min(self.back,self.front.saturating_add(n));
+                self.next()
+            }
+        }
+
+        impl DoubleEndedIterator for $name {
+            #[inline]
+            fn next_back(&mut self) -> Option<Position> {
+                if self.front < self.back {
+                    self.back -= 1;
+                    Some(self.position_at(self.back))
+                } else {
+                    None
+                }
+            }
+        }
+
+        impl ExactSizeIterator for $name {}
+
+        impl FusedIterator for $name {}
+    }
+}
+
+fn rotate_n(direction:MainDirection,rotation:Rotation,n:usize) -> MainDirection {
+    (0..n % 6).fold(direction,|direction,_| direction.rotate(rotation))
+}
+
+/// Iterator returned by `Position::iter_range`, see `Position::in_range`.
+#[derive(Clone,Debug)]
+pub struct RangeIter {
+    center:Position,
+    range:i32,
+    front:(i32,i32),
+    back:(i32,i32),
+    remaining:usize
+}
+
+impl RangeIter {
+    pub fn new(center:Position,range:i32) -> RangeIter {
+        let range = range.abs() ;
+        let n = range as usize ;
+        RangeIter {
+            center,
+            range,
+            front:(range,-range),
+            back:(-range,range),
+            remaining:3 * n * (n + 1) + 1
+        }
+    }
+
+    #[inline]
+    fn row_bounds(&self,dy:i32) -> (i32,i32) {
+        (max(-self.range,-self.range - dy),min(self.range,self.range - dy))
+    }
+}
+
+impl Iterator for RangeIter {
+    type Item = Position ;
+    #[inline]
+    fn next(&mut self) -> Option<Position> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let (dx,dy) = self.front ;
+        self.front = if dx > self.row_bounds(dy).0 {
+            (dx - 1,dy)
+        } else {
+            (self.row_bounds(dy + 1).1,dy + 1)
+        };
+        Some(self.center + (dx,dy))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize,Option<usize>) {
+        (self.remaining,Some(self.remaining))
+    }
+}
+
+impl DoubleEndedIterator for RangeIter {
+    #[inline]
+    fn next_back(&mut self) -> Option<Position> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let (dx,dy) = self.back ;
+        self.back = if dx < self.row_bounds(dy).1 {
+            (dx + 1,dy)
+        } else {
+            (self.row_bounds(dy - 1).0,dy - 1)
+        };
+        Some(self.center + (dx,dy))
+    }
+}
+
+impl ExactSizeIterator for RangeIter {}
+
+impl FusedIterator for RangeIter {}
+
+/// Iterator returned by `Position::iter_star`, see `Position::in_star`.
+#[derive(Clone,Debug)]
+pub struct StarIter {
+    center:Position,
+    front:usize,
+    back:usize
+}
+
+impl StarIter {
+    pub fn new(center:Position,range:i32) -> StarIter {
+        StarIter {
+            center,
+            front:0,
+            back:6 * range.unsigned_abs() as usize + 1
+        }
+    }
+
+    #[inline]
+    fn position_at(&self,index:usize) -> Position {
+        if index == 0 {
+            return self.center;
+        }
+        let range = ((index - 1) / 6 + 1) as i32 ;
+        let direction = match (index - 1) % 6 {
+            0 => NE,
+            1 => E,
+            2 => SE,
+            3 => NW,
+            4 => W,
+            _ => SW
+        };
+        self.center + direction * range
+    }
+}
+
+indexed_shape_iter!(StarIter);
+
+/// Iterator returned by `Position::iter_cone`, see `Position::in_cone`.
+#[derive(Clone,Debug)]
+pub struct ConeIter {
+    center:Position,
+    direction:MainDirection,
+    front:usize,
+    back:usize
+}
+
+impl ConeIter {
+    pub fn new(center:Position,direction:MainDirection,range:i32) -> ConeIter {
+        let (direction,range) = BaseVec(direction,range).normalize().raw();
+        let n = range as usize + 1 ;
+        ConeIter {
+            center,
+            direction,
+            front:0,
+            back:n * n
+        }
+    }
+
+    /// Every range `r` of the cone has `2 * r + 1` positions, so range `r` starts at
+    /// index `r * r`.
+    #[inline]
+    fn position_at(&self,index:usize) -> Position {
+        use pos::Rotation::{CounterClockwise as CCW, Clockwise as CW};
+        let r = index.isqrt() ;
+        let k = (index - r * r) as i32 ;
+        let temp_pos = self.center + self.direction.to_pos() * r as i32 ;
+        if k == 0 {
+            temp_pos
+        } else if k % 2 == 1 {
+            temp_pos + self.direction.rotate(CW).rotate(CW).to_pos() * ((k + 1) / 2)
+        } else {
+            temp_pos + self.direction.rotate(CCW).rotate(CCW).to_pos() * (k / 2)
+        }
+    }
+}
+
+indexed_shape_iter!(ConeIter);
+
+/// Iterator returned by `Position::iter_line`, see `Position::in_line`.
+#[derive(Clone,Debug)]
+pub struct LineIter {
+    origin:Position,
+    direction:MainDirection,
+    front:usize,
+    back:usize
+}
+
+impl LineIter {
+    pub fn new(origin:Position,direction:MainDirection,range:i32) -> LineIter {
+        let (direction,range) = BaseVec(direction,range).normalize().raw();
+        LineIter {
+            origin,
+            direction,
+            front:0,
+            back:range as usize + 1
+        }
+    }
+
+    #[inline]
+    fn position_at(&self,index:usize) -> Position {
+        self.origin + self.direction.to_pos() * index as i32
+    }
+}
+
+indexed_shape_iter!(LineIter);
+
+/// Iterator returned by `Position::iter_ring_with`, see `Position::ring_with`.
+#[derive(Clone,Debug)]
+pub struct RingIter {
+    center:Position,
+    radius:i32,
+    start:MainDirection,
+    rotation:Rotation,
+    front:usize,
+    back:usize
+}
+
+impl RingIter {
+    pub fn new(center:Position,radius:i32,start:MainDirection,rotation:Rotation) -> RingIter {
+        let radius = radius.abs() ;
+        RingIter {
+            center,
+            radius,
+            start,
+            rotation,
+            front:0,
+            back:max(1,6 * radius as usize)
+        }
+    }
+
+    #[inline]
+    fn position_at(&self,index:usize) -> Position {
+        if self.radius == 0 {
+            return self.center;
+        }
+        let radius = self.radius as usize ;
+        let (side,step) = (index / radius, index % radius) ;
+        let corner = rotate_n(self.start,self.rotation,side);
+        let direction = corner.rotate(self.rotation).rotate(self.rotation);
+        self.center + corner.to_pos() * self.radius + direction.to_pos() * step as i32
+    }
+}
+
+indexed_shape_iter!(RingIter);
+
+/// Iterator returned by `Position::iter_spiral_with`, see `Position::spiral_with`.
+#[derive(Clone,Debug)]
+pub struct SpiralIter {
+    center:Position,
+    start:MainDirection,
+    rotation:Rotation,
+    front:usize,
+    back:usize
+}
+
+impl SpiralIter {
+    pub fn new(center:Position,radius:i32,start:MainDirection,rotation:Rotation) -> SpiralIter {
+        let n = radius.unsigned_abs() as usize ;
+        SpiralIter {
+            center,
+            start,
+            rotation,
+            front:0,
+            back:3 * n * (n + 1) + 1
+        }
+    }
+
+    /// The ring of radius `r > 0` starts at index `3 * r * (r - 1) + 1`.
+    #[inline]
+    fn position_at(&self,index:usize) -> Position {
+        if index == 0 {
+            return self.center;
+        }
+        let mut r = ((3 + (12 * index - 3).isqrt()) / 6).max(1) ;
+        while 3 * r * (r + 1) < index {
+            r += 1;
+        }
+        while 3 * r * (r - 1) >= index {
+            r -= 1;
+        }
+        RingIter::new(self.center,r as i32,self.start,self.rotation)
+            .position_at(index - 3 * r * (r - 1) - 1)
+    }
+}
+
+indexed_shape_iter!(SpiralIter);
+
+impl Position {
+    /// Lazy version of `in_range`
+    pub fn iter_range(self,range:i32) -> RangeIter {
+        RangeIter::new(self,range)
+    }
+
+    /// Lazy version of `in_star`
+    pub fn iter_star(self,range:i32) -> StarIter {
+        StarIter::new(self,range)
+    }
+
+    /// Lazy version of `in_cone`
+    pub fn iter_cone(self,direction:MainDirection,range:i32) -> ConeIter {
+        ConeIter::new(self,direction,range)
+    }
+
+    /// Lazy version of `in_line`
+    pub fn iter_line(self,direction:MainDirection,range:i32) -> LineIter {
+        LineIter::new(self,direction,range)
+    }
+
+    /// Lazy version of `ring`
+    pub fn iter_ring(self,radius:i32) -> RingIter {
+        RingIter::new(self,radius,MainDirection::NNE,Rotation::Clockwise)
+    }
+
+    /// Lazy version of `ring_with`
+    pub fn iter_ring_with(self,radius:i32,start:MainDirection,rotation:Rotation) -> RingIter {
+        RingIter::new(self,radius,start,rotation)
+    }
+
+    /// Lazy version of `spiral`
+    pub fn iter_spiral(self,radius:i32) -> SpiralIter {
+        SpiralIter::new(self,radius,MainDirection::NNE,Rotation::Clockwise)
+    }
+
+    /// Lazy version of `spiral_with`
+    pub fn iter_spiral_with(self,radius:i32,start:MainDirection,rotation:Rotation) -> SpiralIter {
+        SpiralIter::new(self,radius,start,rotation)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use pos::* ;
+
+    #[test]
+    fn exact_size(){
+        let center = Position::new(-1,4);
+        let mut iter = center.iter_range(3);
+        assert_eq!(iter.len(),37);
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.len(),35);
+        assert_eq!(center.iter_cone(MainDirection::W,-2).len(),9);
+        assert_eq!(center.iter_star(0).len(),1);
+        assert_eq!(center.iter_ring(4).len(),24);
+        assert_eq!(center.iter_spiral(4).len(),61);
+    }
+
+    #[test]
+    fn double_ended(){
+        let center = Position::new(2,2);
+        let mut iter = center.iter_range(2);
+        let mut collected = Vec::new();
+        while let Some(position) = iter.next() {
+            collected.push(position);
+            if let Some(position) = iter.next_back() {
+                collected.push(position);
+            }
+        }
+        assert_eq!(collected.len(),19);
+        collected.sort_by_key(|p| (p.x,p.y));
+        collected.dedup();
+        assert_eq!(collected.len(),19);
+
+        let spiral : Vec<Position> = center.iter_spiral(3).rev().collect();
+        let mut expected = center.spiral(3);
+        expected.reverse();
+        assert_eq!(spiral,expected);
+    }
+
+    #[test]
+    fn fused(){
+        let mut iter = Position::new(0,0).iter_line(MainDirection::E,1);
+        assert_eq!(iter.next(),Some(Position::new(0,0)));
+        assert_eq!(iter.next_back(),Some(Position::new(1,0)));
+        assert_eq!(iter.next(),None);
+        assert_eq!(iter.next_back(),None);
+        assert_eq!(iter.next(),None);
+    }
+
+    #[test]
+    fn nth(){
+        let center = Position::new(0,0);
+        let ring = center.ring(3);
+        assert_eq!(center.iter_ring(3).nth(7),Some(ring[7]));
+        assert_eq!(center.iter_ring(3).nth(18),None);
+        let spiral = center.spiral(4);
+        for (i,&position) in spiral.iter().enumerate() {
+            assert_eq!(center.iter_spiral(4).nth(i),Some(position));
+        }
+    }
+}